@@ -81,9 +81,9 @@ fn main() {
     let second_get2 = provider.get_boxed::<dyn SecondDep>().unwrap();
     let second_get3 = provider.get_boxed::<dyn SecondDep>().unwrap();
 
-    // getting a scoped function directly from a ServiceProvider
-    // without creating a new scope will make them looks like a singleton
-    // since the ProviderService itself act like a global scope.
+    // getting a scoped service directly from a ServiceProvider without
+    // creating a scope builds a new instance every time, like a transient,
+    // since there's no scope to cache it in.
     let third_get1 = provider.get_boxed::<dyn ThirdDep>().unwrap();
     let third_get2 = provider.get_boxed::<dyn ThirdDep>().unwrap();
     let third_get3 = provider.get_boxed::<dyn ThirdDep>().unwrap();
@@ -141,17 +141,17 @@ fn main() {
     println!("\n");
 
     println!(
-        "ThirdDepImpl (global scope) memory address on first get attempt {:p}\nwith deps: {}",
+        "ThirdDepImpl (root, not cached) memory address on first get attempt {:p}\nwith deps: {}",
         third_get1,
         third_get1.debug_to_str()
     );
     println!(
-        "ThirdDepImpl (global scope) memory address on second get attempt {:p}\nwith deps: {}",
+        "ThirdDepImpl (root, not cached) memory address on second get attempt {:p}\nwith deps: {}",
         third_get2,
         third_get2.debug_to_str()
     );
     println!(
-        "ThirdDepImpl (global scope) memory address on third get attempt {:p}\nwith deps: {}",
+        "ThirdDepImpl (root, not cached) memory address on third get attempt {:p}\nwith deps: {}",
         third_get3,
         third_get3.debug_to_str()
     );
@@ -0,0 +1,83 @@
+//! An optional per-thread "current" provider.
+//!
+//! Resolving from an ambient provider is the service locator pattern: the
+//! dependencies of a function no longer show up in its signature, a missing
+//! registration is only noticed when the code runs, and tests have to make
+//! sure the right provider is current. Prefer passing the provider (or the
+//! resolved services) explicitly, and keep this for glue code that can't.
+
+use std::{any::Any, cell::RefCell, rc::Rc};
+
+use crate::{Error, ServiceProvider};
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<ServiceProvider>>> = const { RefCell::new(None) };
+}
+
+/// Returned by [`ServiceProvider::set_current`], puts the previously current
+/// provider back when dropped.
+#[must_use = "the provider stops being current when the guard is dropped"]
+#[derive(Debug)]
+pub struct CurrentProviderGuard {
+    previous: Option<Rc<ServiceProvider>>,
+}
+
+impl Drop for CurrentProviderGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+impl ServiceProvider {
+    /// Makes this provider the current one of the calling thread until the
+    /// returned guard is dropped. Guards nest.
+    pub fn set_current(self: Rc<Self>) -> CurrentProviderGuard {
+        self.remember_rc(&self);
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(self));
+        CurrentProviderGuard { previous }
+    }
+}
+
+/// The current provider of the calling thread, if any.
+pub fn current() -> Option<Rc<ServiceProvider>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Resolves `Box<T>` from the current provider of the calling thread.
+pub fn resolve_boxed<T: ?Sized + Any + 'static>() -> Result<Rc<Box<T>>, Error> {
+    current()
+        .ok_or_else(|| Error::Unknown("no current service provider".to_string()))?
+        .get_boxed::<T>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{current, resolve_boxed};
+    use crate::ServiceCollection;
+
+    fn provider_with(value: u32) -> Rc<crate::ServiceProvider> {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(move |_| Box::new(value));
+        Rc::new(collection.build())
+    }
+
+    #[test]
+    fn nested_guards_restore_the_previous_provider() {
+        assert!(resolve_boxed::<u32>().is_err());
+
+        let outer = provider_with(1).set_current();
+        assert_eq!(**resolve_boxed::<u32>().unwrap(), 1);
+
+        {
+            let _inner = provider_with(2).set_current();
+            assert_eq!(**resolve_boxed::<u32>().unwrap(), 2);
+        }
+
+        assert_eq!(**resolve_boxed::<u32>().unwrap(), 1);
+        drop(outer);
+        assert!(current().is_none());
+    }
+}
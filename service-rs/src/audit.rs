@@ -0,0 +1,162 @@
+use std::{collections::HashMap, rc::Rc, sync::Arc};
+
+use crate::{Lifetime, ServiceCollection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditCategory {
+    /// Registered more than once, only the last registration is used.
+    DuplicateRegistration,
+    /// A scoped service resolved by a singleton factory, which runs at the
+    /// root, so the singleton never sees the instance of any scope.
+    ScopedResolvedFromRoot,
+    /// A trait object registered through the unboxed methods as `Rc<dyn T>`
+    /// or `Arc<dyn T>`, which `get_boxed::<dyn T>()` doesn't find.
+    UnboxedTraitObject,
+}
+
+/// A potential issue reported by [`ServiceCollection::audit`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AuditWarning {
+    pub category: AuditCategory,
+    pub type_name: &'static str,
+}
+
+impl ServiceCollection {
+    /// Pre-flight check for registrations that are probably mistakes, meant to
+    /// run in a test. Warnings are sorted by category, then type name.
+    ///
+    /// Finding scoped services resolved by singletons takes the runtime
+    /// wiring, so every factory runs once against a throwaway provider, as
+    /// for [`ServiceProvider::trace_graph`](crate::ServiceProvider::trace_graph).
+    pub fn audit(&self) -> Vec<AuditWarning> {
+        let mut warnings = self
+            .replaced
+            .iter()
+            .map(|type_name| AuditWarning {
+                category: AuditCategory::DuplicateRegistration,
+                type_name,
+            })
+            .collect::<Vec<_>>();
+
+        // only the outermost type counts, `Vec<Rc<Box<dyn T>>>` and the like are
+        // legitimate keys
+        let pointers = [
+            std::any::type_name::<Rc<()>>().trim_end_matches("()>"),
+            std::any::type_name::<Arc<()>>().trim_end_matches("()>"),
+        ];
+        warnings.extend(
+            self.services
+                .values()
+                .filter(|descriptor| {
+                    pointers.iter().any(|pointer| {
+                        descriptor
+                            .type_name
+                            .strip_prefix(pointer)
+                            .is_some_and(|pointee| pointee.starts_with("dyn "))
+                    })
+                })
+                .map(|descriptor| AuditWarning {
+                    category: AuditCategory::UnboxedTraitObject,
+                    type_name: descriptor.type_name,
+                }),
+        );
+
+        let lifetimes = self
+            .services
+            .values()
+            .map(|descriptor| (descriptor.type_name, descriptor.lifetime.clone()))
+            .collect::<HashMap<_, _>>();
+        let graph = self.clone().build().trace_graph();
+        warnings.extend(
+            graph
+                .edges()
+                .iter()
+                .filter(|(dependent, dependency)| {
+                    lifetimes.get(dependent) == Some(&Lifetime::Singleton)
+                        && lifetimes.get(dependency) == Some(&Lifetime::Scoped)
+                })
+                .map(|(_, dependency)| AuditWarning {
+                    category: AuditCategory::ScopedResolvedFromRoot,
+                    type_name: dependency,
+                }),
+        );
+
+        warnings.sort_unstable();
+        warnings.dedup();
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{AuditCategory, AuditWarning};
+    use crate::ServiceCollection;
+
+    trait Session {}
+    struct SessionImpl;
+    impl Session for SessionImpl {}
+
+    #[test]
+    fn audit_reports_each_category() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1))
+            .add_singleton_boxed::<u32, _>(|_| Box::new(2))
+            .add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl))
+            .add_singleton_boxed::<u64, _>(|provider| {
+                provider.get_boxed::<dyn Session>().unwrap();
+                Box::new(3)
+            })
+            .add_singleton::<Rc<dyn Session>, _>(|_| {
+                Box::new(Rc::new(SessionImpl) as Rc<dyn Session>)
+            })
+            .mock_boxed::<u64>(Box::new(4));
+
+        let warnings = collection.audit();
+
+        assert_eq!(
+            warnings,
+            vec![
+                AuditWarning {
+                    category: AuditCategory::DuplicateRegistration,
+                    type_name: std::any::type_name::<Box<u32>>(),
+                },
+                AuditWarning {
+                    category: AuditCategory::UnboxedTraitObject,
+                    type_name: std::any::type_name::<Rc<dyn Session>>(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_finds_scoped_services_resolved_by_singletons() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl))
+            .add_singleton_boxed::<u64, _>(|provider| {
+                provider.get_boxed::<dyn Session>().unwrap();
+                Box::new(3)
+            });
+
+        assert_eq!(
+            collection.audit(),
+            vec![AuditWarning {
+                category: AuditCategory::ScopedResolvedFromRoot,
+                type_name: std::any::type_name::<Box<dyn Session>>(),
+            }]
+        );
+    }
+
+    #[test]
+    fn audit_accepts_trait_objects_nested_in_other_keys() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<dyn Session, _>(|_| Box::new(SessionImpl))
+            .add_collection_boxed::<dyn Session>();
+
+        assert!(collection.audit().is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+//! Resolving the container itself.
+//!
+//! A service that takes the provider and resolves from it later is a service
+//! locator: its real dependencies no longer show up in its factory, and a
+//! missing registration is only noticed when the lookup runs. Keep it for
+//! services that genuinely pick what to resolve at runtime (a dynamic
+//! dispatcher, a plugin host, ...) and inject concrete dependencies everywhere
+//! else.
+//!
+//! The `Rc<ScopedServiceProvider>` handed out inside a scope shares that
+//! scope's cache. A scoped service that keeps it is cached in what it holds on
+//! to, an `Rc` cycle that leaks the scope and everything it cached. Scoped
+//! factories should resolve through it and let it go; transients, which aren't
+//! cached in the scope, may keep it.
+
+use std::{
+    any::{Any, TypeId},
+    rc::Rc,
+};
+
+use crate::{ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+impl ServiceCollection {
+    /// Builds the provider behind an `Rc`, so that `get::<Rc<ServiceProvider>>()`
+    /// can hand it out right away.
+    pub fn build_rc(self) -> Rc<ServiceProvider> {
+        let provider = Rc::new(self.build());
+        provider.remember_rc(&provider);
+        provider
+    }
+}
+
+impl ServiceProvider {
+    // a provider only knows its own `Rc` once it was handed one, through
+    // `build_rc`, `create_scope` or `set_current`
+    pub(crate) fn remember_rc(&self, provider: &Rc<ServiceProvider>) {
+        *self.this.borrow_mut() = Rc::downgrade(provider);
+    }
+
+    // `Rc<ServiceProvider>` and `Rc<ScopedServiceProvider>` resolve to the
+    // container unless they were registered explicitly
+    pub(crate) fn resolve_container(&self, type_id: TypeId) -> Option<Rc<dyn Any>> {
+        let provider = self.this.borrow().upgrade()?;
+
+        if type_id == TypeId::of::<Rc<ServiceProvider>>() {
+            return Some(Rc::new(provider));
+        }
+
+        if type_id == TypeId::of::<Rc<ScopedServiceProvider>>() {
+            let active = self.scopes.borrow().last().cloned()?;
+
+            let scope = ScopedServiceProvider {
+                provider,
+                id: active.id,
+                is_view: true,
+                services: active.services,
+                inherited: active.inherited,
+                locals: active.locals,
+                creation_order: active.creation_order,
+                keyed: Default::default(),
+                disposers: Default::default(),
+            };
+
+            return Some(Rc::new(Rc::new(scope)));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+    struct Dispatcher(Rc<ServiceProvider>);
+
+    #[test]
+    fn factories_can_resolve_the_provider_itself() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(7))
+            .add_transient::<Dispatcher, _>(|provider| {
+                Box::new(Dispatcher(
+                    (*provider.get::<Rc<ServiceProvider>>().unwrap()).clone(),
+                ))
+            });
+
+        let provider = collection.build_rc();
+        let dispatcher = provider.get::<Dispatcher>().unwrap();
+
+        assert!(Rc::ptr_eq(&dispatcher.0, &provider));
+        assert_eq!(**dispatcher.0.get_boxed::<u32>().unwrap(), 7);
+    }
+
+    #[test]
+    fn scoped_factories_see_the_active_scope() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<u32, _>(|_| Box::new(1))
+            .add_scoped_boxed::<u64, _>(|provider| {
+                let scope = provider.get::<Rc<ScopedServiceProvider>>().unwrap();
+                Box::new(**scope.get_boxed::<u32>().unwrap() as u64)
+            });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let inner = scope.get_boxed::<u32>().unwrap();
+        scope.get_boxed::<u64>().unwrap();
+
+        assert!(Rc::ptr_eq(&inner, &scope.get_boxed::<u32>().unwrap()));
+        assert!(provider.get::<Rc<ScopedServiceProvider>>().is_err());
+    }
+
+    // a transient and a scoped service holding on to the scope
+    #[allow(dead_code)]
+    struct Locator(Rc<ScopedServiceProvider>);
+    #[allow(dead_code)]
+    struct Keeper(Rc<ScopedServiceProvider>);
+
+    #[test]
+    fn only_scoped_services_keeping_the_scope_leak_it() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<u32, _>(|_| Box::new(1))
+            .add_transient::<Locator, _>(|provider| {
+                Box::new(Locator(
+                    (*provider.get::<Rc<ScopedServiceProvider>>().unwrap()).clone(),
+                ))
+            })
+            .add_scoped::<Keeper, _>(|provider| {
+                Box::new(Keeper(
+                    (*provider.get::<Rc<ScopedServiceProvider>>().unwrap()).clone(),
+                ))
+            });
+
+        let provider = Rc::new(collection.build());
+
+        let scope = provider.create_scope();
+        let cached = Rc::downgrade(&scope.get_boxed::<u32>().unwrap());
+        drop(scope.get::<Locator>().unwrap());
+        drop(scope);
+        assert!(cached.upgrade().is_none());
+
+        // the scope's cache holds the keeper, which holds the cache
+        let scope = provider.create_scope();
+        let cached = Rc::downgrade(&scope.get_boxed::<u32>().unwrap());
+        scope.get::<Keeper>().unwrap();
+        drop(scope);
+        assert!(cached.upgrade().is_some());
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    any::{Any, TypeId},
+    rc::Rc,
+};
+
+use crate::{Error, ServiceCollection, ServiceProvider};
+
+impl ServiceCollection {
+    /// Wraps the instances built by the current registration of `T` with
+    /// `decorator`, which receives the inner instance and the provider. Does
+    /// nothing if `T` isn't registered yet.
+    ///
+    /// Decorators stack: the last one added is the outermost. Instances added
+    /// through `mock_boxed` are not decorated.
+    pub fn decorate_boxed<T: ?Sized + 'static>(
+        &mut self,
+        decorator: impl Fn(Box<T>, &ServiceProvider) -> Box<T> + 'static,
+    ) -> &mut Self {
+        let Some(descriptor) = self.services.get_mut(&TypeId::of::<Box<T>>()) else {
+            return self;
+        };
+
+        let type_name = descriptor.type_name;
+        let inner = std::mem::replace(
+            &mut descriptor.factory,
+            Rc::new(move |_| Err(Error::Unknown(type_name.to_string()))),
+        );
+
+        descriptor.factory = Rc::new(move |provider| {
+            match inner(provider)?.downcast::<Box<T>>() {
+                Ok(service) => Ok(Box::new(decorator(*service, provider)) as Box<dyn Any>),
+                // shared instances were constructed elsewhere, hand them out as-is
+                Err(instance) => Ok(instance),
+            }
+        });
+
+        self
+    }
+
+    /// Same as `decorate_boxed`, but applies the decorator identified by `key`
+    /// at most once per service type, so modules composing the same decorator
+    /// don't wrap the service twice.
+    pub fn decorate_once_boxed<T: ?Sized + 'static>(
+        &mut self,
+        key: &'static str,
+        decorator: impl Fn(Box<T>, &ServiceProvider) -> Box<T> + 'static,
+    ) -> &mut Self {
+        let type_id = TypeId::of::<Box<T>>();
+
+        if !self.services.contains_key(&type_id) || !self.decorators.insert((type_id, key)) {
+            return self;
+        }
+
+        self.decorate_boxed(decorator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ServiceCollection;
+
+    trait Repository {
+        fn describe(&self) -> String;
+    }
+
+    struct RepositoryImpl;
+    impl Repository for RepositoryImpl {
+        fn describe(&self) -> String {
+            "repository".to_string()
+        }
+    }
+
+    struct Logged(Box<dyn Repository>);
+    impl Repository for Logged {
+        fn describe(&self) -> String {
+            format!("logged({})", self.0.describe())
+        }
+    }
+
+    #[test]
+    fn decorate_once_boxed_skips_repeated_keys() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Repository, _>(|_| Box::new(RepositoryImpl));
+
+        for _ in 0..2 {
+            collection.decorate_once_boxed::<dyn Repository>("logging", |inner, _| {
+                Box::new(Logged(inner))
+            });
+        }
+
+        let provider = collection.build();
+        assert_eq!(
+            provider.get_boxed::<dyn Repository>().unwrap().describe(),
+            "logged(repository)"
+        );
+    }
+}
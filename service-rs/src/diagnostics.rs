@@ -0,0 +1,291 @@
+use std::{
+    any::{Any, TypeId},
+    collections::{BTreeSet, HashMap},
+    rc::Rc,
+};
+
+use crate::{
+    Lifetime, ScopedServiceProvider, ServiceCollection, ServiceProvider, UnderConstruction,
+};
+
+impl ServiceCollection {
+    pub(crate) fn type_names<'a>(
+        &self,
+        type_ids: impl IntoIterator<Item = &'a TypeId>,
+    ) -> Vec<&'static str> {
+        let mut type_names = type_ids
+            .into_iter()
+            .filter_map(|type_id| self.services.get(type_id))
+            .map(|descriptor| descriptor.type_name)
+            .collect::<Vec<_>>();
+
+        type_names.sort_unstable();
+        type_names
+    }
+
+    // the registered instances in `cache`, sorted by type name. handed out as a
+    // copy so callers can resolve while walking them
+    fn cached_entries(
+        &self,
+        cache: &HashMap<TypeId, Rc<dyn Any>>,
+    ) -> Vec<(&'static str, Rc<dyn Any>)> {
+        let mut entries = cache
+            .iter()
+            .filter(|(_, service)| !service.is::<UnderConstruction>())
+            .filter_map(|(type_id, service)| {
+                let descriptor = self.services.get(type_id)?;
+                Some((descriptor.type_name, service.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_unstable_by_key(|(type_name, _)| *type_name);
+        entries
+    }
+
+    /// Type names of the services registered with `lifetime`, sorted.
+    pub fn descriptors_by_lifetime(
+        &self,
+        lifetime: Lifetime,
+    ) -> impl Iterator<Item = &'static str> {
+        let matching = self
+            .services
+            .iter()
+            .filter(move |(_, descriptor)| descriptor.lifetime == lifetime)
+            .map(|(type_id, _)| type_id);
+
+        self.type_names(matching).into_iter()
+    }
+
+    /// Every registration as a `(type name, lifetime)` pair. Factories can't be
+    /// compared, but two collections with equal keys wire the same types with
+    /// the same lifetimes, which is what a test guarding a refactor of the
+    /// registration code usually wants to assert.
+    pub fn registration_keys(&self) -> BTreeSet<(&'static str, Lifetime)> {
+        self.services
+            .values()
+            .map(|descriptor| (descriptor.type_name, descriptor.lifetime.clone()))
+            .collect()
+    }
+}
+
+impl ServiceProvider {
+    /// Type names of the instances cached by the root provider, sorted.
+    pub fn cached_types(&self) -> Vec<&'static str> {
+        self.collection.type_names(self.services.borrow().keys())
+    }
+
+    /// Strong count of every instance cached by the root provider, sorted by
+    /// type name. The cache holds one of the counted handles, so a count well
+    /// above the number of known holders points at leaked `Rc`s.
+    pub fn strong_counts(&self) -> Vec<(&'static str, usize)> {
+        let mut counts = self
+            .services
+            .borrow()
+            .iter()
+            .filter_map(|(type_id, service)| {
+                let descriptor = self.collection.services.get(type_id)?;
+                Some((descriptor.type_name, Rc::strong_count(service)))
+            })
+            .collect::<Vec<_>>();
+
+        counts.sort_unstable();
+        counts
+    }
+
+    /// Calls `f` with every instance cached by the root provider and its type
+    /// name, sorted by type name, e.g. to downcast them to a health check.
+    /// Instances without a registration (`get_or_register_singleton_boxed`)
+    /// are skipped.
+    pub fn for_each_cached(&self, mut f: impl FnMut(&'static str, &Rc<dyn Any>)) {
+        let entries = self.collection.cached_entries(&self.services.borrow());
+
+        for (type_name, service) in &entries {
+            f(type_name, service);
+        }
+    }
+}
+
+impl ScopedServiceProvider {
+    /// Type names of the instances cached by this scope, sorted.
+    pub fn cached_types(&self) -> Vec<&'static str> {
+        self.provider
+            .collection
+            .type_names(self.services.borrow().keys())
+    }
+
+    /// Same as [`ServiceProvider::for_each_cached`] for the instances cached by
+    /// this scope.
+    pub fn for_each_cached(&self, mut f: impl FnMut(&'static str, &Rc<dyn Any>)) {
+        let entries = self
+            .provider
+            .collection
+            .cached_entries(&self.services.borrow());
+
+        for (type_name, service) in &entries {
+            f(type_name, service);
+        }
+    }
+
+    /// Whether the root provider already holds an instance of `T`, i.e. whether
+    /// resolving it from here will reuse it instead of running its factory.
+    pub fn root_has_cached<T: Any>(&self) -> bool {
+        self.provider
+            .services
+            .borrow()
+            .contains_key(&TypeId::of::<T>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{Lifetime, ServiceCollection};
+
+    trait Session {}
+    struct SessionImpl;
+    impl Session for SessionImpl {}
+
+    trait Handler {}
+    #[allow(dead_code)]
+    struct HandlerImpl(Rc<Box<dyn Session>>);
+    impl Handler for HandlerImpl {}
+
+    #[test]
+    fn cached_types_report_where_instances_live() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+        collection.add_scoped_boxed::<dyn Handler, _>(|provider| {
+            Box::new(HandlerImpl(provider.get_boxed::<dyn Session>().unwrap()))
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.get_boxed::<dyn Handler>().unwrap();
+
+        let mut expected = vec![
+            std::any::type_name::<Box<dyn Handler>>(),
+            std::any::type_name::<Box<dyn Session>>(),
+        ];
+        expected.sort_unstable();
+
+        assert_eq!(scope.cached_types(), expected);
+        assert!(provider.cached_types().is_empty());
+    }
+
+    #[test]
+    fn root_has_cached_reports_singletons_built_by_the_root() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        assert!(!scope.root_has_cached::<Box<dyn Session>>());
+
+        scope.get_boxed::<dyn Session>().unwrap();
+        assert!(scope.root_has_cached::<Box<dyn Session>>());
+    }
+
+    #[test]
+    fn strong_counts_include_the_cached_handle() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+
+        let provider = collection.build();
+        let held = provider.get_boxed::<dyn Session>().unwrap();
+
+        assert_eq!(
+            provider.strong_counts(),
+            vec![(std::any::type_name::<Box<dyn Session>>(), 2)]
+        );
+
+        drop(held);
+        assert_eq!(provider.strong_counts()[0].1, 1);
+    }
+
+    trait HealthCheck {
+        fn healthy(&self) -> bool;
+    }
+
+    impl HealthCheck for u32 {
+        fn healthy(&self) -> bool {
+            *self > 0
+        }
+    }
+
+    #[test]
+    fn for_each_cached_walks_the_instances_of_a_scope() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl))
+            .add_scoped_boxed::<u32, _>(|_| Box::new(0))
+            .add_singleton_boxed::<u64, _>(|_| Box::new(1));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.get_boxed::<dyn Session>().unwrap();
+        scope.get_boxed::<u32>().unwrap();
+        scope.get_boxed::<u64>().unwrap();
+
+        let mut visited = Vec::new();
+        let mut report = Vec::new();
+        scope.for_each_cached(|type_name, service| {
+            visited.push(type_name);
+            if let Some(check) = service.downcast_ref::<Box<u32>>() {
+                report.push((type_name, check.healthy()));
+            }
+        });
+
+        assert_eq!(visited, scope.cached_types());
+        assert_eq!(report, vec![(std::any::type_name::<Box<u32>>(), false)]);
+
+        let mut root = Vec::new();
+        provider.for_each_cached(|type_name, _| root.push(type_name));
+        assert_eq!(root, vec![std::any::type_name::<Box<u64>>()]);
+    }
+
+    #[test]
+    fn registration_keys_compare_the_wiring_only() {
+        let mut direct = ServiceCollection::new();
+        direct
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1))
+            .add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+
+        let mut refactored = ServiceCollection::new();
+        refactored
+            .add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl))
+            .add_singleton_boxed::<u32, _>(|_| Box::new(2));
+
+        assert_eq!(direct.registration_keys(), refactored.registration_keys());
+
+        refactored.add_transient_boxed::<u32, _>(|_| Box::new(1));
+        assert_ne!(direct.registration_keys(), refactored.registration_keys());
+    }
+
+    #[test]
+    fn descriptors_by_lifetime_filters_registrations() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+        collection.add_scoped_boxed::<u64, _>(|_| Box::new(2));
+
+        let mut scoped = vec![
+            std::any::type_name::<Box<dyn Session>>(),
+            std::any::type_name::<Box<u64>>(),
+        ];
+        scoped.sort_unstable();
+
+        assert_eq!(
+            collection
+                .descriptors_by_lifetime(Lifetime::Scoped)
+                .collect::<Vec<_>>(),
+            scoped
+        );
+        assert_eq!(
+            collection
+                .descriptors_by_lifetime(Lifetime::Transient)
+                .count(),
+            0
+        );
+    }
+}
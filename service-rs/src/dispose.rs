@@ -0,0 +1,244 @@
+use std::{
+    any::{Any, TypeId},
+    rc::Rc,
+};
+
+use crate::{
+    RootEntry, ScopedServiceProvider, ServiceCollection, ServiceDescriptor, ServiceProvider,
+};
+
+/// Teardown hook for services that hold resources (flushing a logger, closing
+/// a connection, ...).
+pub trait Disposable {
+    fn dispose(&self);
+}
+
+pub(crate) type ServiceDisposer = Rc<dyn Fn(&dyn Any)>;
+
+impl ServiceCollection {
+    /// Marks the registered `T` as [`Disposable`], the container calls
+    /// `dispose` on its cached instances when they're torn down. Ordered and
+    /// set implementations registered as `T` are marked too.
+    ///
+    /// Does nothing for registrations of `T` added afterwards.
+    pub fn mark_disposable<T: Disposable + Any + 'static>(&mut self) -> &mut Self {
+        let disposer: ServiceDisposer = Rc::new(|service| {
+            if let Some(service) = service.downcast_ref::<T>() {
+                service.dispose();
+            }
+        });
+
+        for descriptor in self.registrations_mut(TypeId::of::<T>(), std::any::type_name::<T>()) {
+            descriptor.disposer = Some(disposer.clone());
+        }
+
+        self
+    }
+
+    /// Same as [`ServiceCollection::mark_disposable`] for services registered
+    /// through the `_boxed` methods, `dyn Trait` qualifies when `Disposable` is
+    /// one of its supertraits.
+    pub fn mark_disposable_boxed<T: ?Sized + Disposable + 'static>(&mut self) -> &mut Self {
+        let disposer: ServiceDisposer = Rc::new(|service| {
+            if let Some(service) = service.downcast_ref::<Box<T>>() {
+                service.dispose();
+            }
+        });
+
+        let type_name = std::any::type_name::<Box<T>>();
+        for descriptor in self.registrations_mut(TypeId::of::<Box<T>>(), type_name) {
+            descriptor.disposer = Some(disposer.clone());
+        }
+
+        self
+    }
+
+    // the registration of `type_id` along with every ordered or set
+    // implementation built as `type_name`
+    pub(crate) fn registrations_mut(
+        &mut self,
+        type_id: TypeId,
+        type_name: &'static str,
+    ) -> impl Iterator<Item = &mut ServiceDescriptor> {
+        let members = self
+            .enumerables
+            .values_mut()
+            .flatten()
+            .map(|member| &mut member.descriptor)
+            .filter(move |descriptor| descriptor.type_name == type_name);
+
+        self.services.get_mut(&type_id).into_iter().chain(members)
+    }
+}
+
+impl ServiceProvider {
+    /// Disposes every instance cached by the root provider, ordered and set
+    /// implementations included, most recently created first, and clears the
+    /// cache. Later resolutions build fresh instances.
+    pub fn dispose_all(&self) {
+        for (descriptor, service) in self.take_cached_in_reverse() {
+            if let Some(dispose) = descriptor.and_then(|descriptor| descriptor.disposer.as_ref()) {
+                dispose(service.as_ref());
+            }
+        }
+    }
+
+    // empties the root caches, most recently created instance first, along
+    // with the registration each was built from (singletons registered at
+    // runtime have none)
+    pub(crate) fn take_cached_in_reverse(&self) -> Vec<(Option<&ServiceDescriptor>, Rc<dyn Any>)> {
+        let order = self.creation_order.take();
+        let mut services = self.services.take();
+        let mut members = self.enumerable_cache.take();
+
+        order
+            .into_iter()
+            .rev()
+            .filter_map(|entry| match entry {
+                RootEntry::Service(type_id) => Some((
+                    self.collection.services.get(&type_id),
+                    services.remove(&type_id)?,
+                )),
+                RootEntry::Member(type_id, index) => Some((
+                    Some(&self.collection.enumerables[&type_id][index].descriptor),
+                    members.remove(&(type_id, index))?,
+                )),
+            })
+            .collect()
+    }
+}
+
+impl ScopedServiceProvider {
+    /// Drops the scoped instances cached by this scope whose type name matches
+    /// `pred`, disposing the ones marked [`Disposable`]. They are built again
+    /// on their next resolution, which gives long-lived scopes shorter
+    /// sub-lifetimes (per message within a connection, ...).
+    pub fn reset_where(&self, pred: impl Fn(&'static str) -> bool) {
+        let services = &self.provider.collection.services;
+        let removed = {
+            let mut cache = self.services.borrow_mut();
+            let matching = cache
+                .keys()
+                .filter(|type_id| {
+                    services
+                        .get(type_id)
+                        .is_some_and(|descriptor| pred(descriptor.type_name))
+                })
+                .copied()
+                .collect::<Vec<_>>();
+
+            matching
+                .into_iter()
+                .filter_map(|type_id| Some((type_id, cache.remove(&type_id)?)))
+                .collect::<Vec<_>>()
+        };
+
+        // the cache isn't borrowed anymore, disposers may resolve from the scope
+        for (type_id, service) in removed {
+            if let Some(dispose) = services[&type_id].disposer.as_ref() {
+                dispose(service.as_ref());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::Disposable;
+    use crate::ServiceCollection;
+
+    trait Logger: Disposable {}
+
+    struct LoggerImpl(&'static str, Rc<RefCell<Vec<&'static str>>>);
+    impl Logger for LoggerImpl {}
+    impl Disposable for LoggerImpl {
+        fn dispose(&self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    struct Metrics(Rc<RefCell<Vec<&'static str>>>);
+    impl Disposable for Metrics {
+        fn dispose(&self) {
+            self.0.borrow_mut().push("metrics");
+        }
+    }
+
+    #[test]
+    fn dispose_all_runs_in_reverse_creation_order() {
+        let disposed = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = disposed.clone();
+        collection
+            .add_singleton_boxed::<dyn Logger, _>(move |_| {
+                Box::new(LoggerImpl("logger", log.clone()))
+            })
+            .mark_disposable_boxed::<dyn Logger>();
+        let log = disposed.clone();
+        collection
+            .add_singleton::<Metrics, _>(move |_| Box::new(Metrics(log.clone())))
+            .mark_disposable::<Metrics>();
+
+        let provider = collection.build();
+        provider.get_boxed::<dyn Logger>().unwrap();
+        provider.get::<Metrics>().unwrap();
+
+        provider.dispose_all();
+
+        assert_eq!(*disposed.borrow(), vec!["metrics", "logger"]);
+        assert!(provider.services.borrow().is_empty());
+    }
+
+    #[test]
+    fn dispose_all_includes_ordered_implementations() {
+        let disposed = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = disposed.clone();
+        collection.add_singleton::<Metrics, _>(move |_| Box::new(Metrics(log.clone())));
+        for name in ["first", "second"] {
+            let log = disposed.clone();
+            collection.add_singleton_ordered_boxed::<dyn Logger, _>(0, move |_| {
+                Box::new(LoggerImpl(name, log.clone()))
+            });
+        }
+        collection
+            .mark_disposable::<Metrics>()
+            .mark_disposable_boxed::<dyn Logger>();
+
+        let provider = collection.build();
+        provider.get::<Metrics>().unwrap();
+        provider.get_all_boxed::<dyn Logger>().unwrap();
+
+        provider.dispose_all();
+
+        assert_eq!(*disposed.borrow(), vec!["second", "first", "metrics"]);
+        assert_eq!(provider.cached_instances(), 0);
+    }
+
+    #[test]
+    fn reset_where_drops_only_matching_scoped_instances() {
+        let disposed = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = disposed.clone();
+        collection
+            .add_scoped::<Metrics, _>(move |_| Box::new(Metrics(log.clone())))
+            .mark_disposable::<Metrics>()
+            .add_scoped_boxed::<u32, _>(|_| Box::new(1));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let metrics = scope.get::<Metrics>().unwrap();
+        let connection = scope.get_boxed::<u32>().unwrap();
+
+        scope.reset_where(|type_name| type_name.ends_with("Metrics"));
+
+        assert_eq!(*disposed.borrow(), vec!["metrics"]);
+        assert!(!Rc::ptr_eq(&metrics, &scope.get::<Metrics>().unwrap()));
+        assert!(Rc::ptr_eq(&connection, &scope.get_boxed::<u32>().unwrap()));
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    any::{Any, TypeId},
+    future::Future,
+    ops::Deref,
+    pin::Pin,
+    rc::Rc,
+};
+
+use crate::{ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+/// Asynchronous counterpart of [`Disposable`](crate::Disposable), for teardown
+/// that has to await (closing a database pool, flushing a remote sink, ...).
+#[allow(async_fn_in_trait)]
+pub trait AsyncDisposable {
+    async fn dispose(&self);
+}
+
+pub(crate) type AsyncServiceDisposer = Rc<dyn Fn(Rc<dyn Any>) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+impl ServiceCollection {
+    /// Marks the registered `T` as [`AsyncDisposable`], awaited by
+    /// [`ServiceProvider::dispose_all_async`].
+    ///
+    /// Does nothing for registrations of `T` added afterwards.
+    pub fn mark_async_disposable<T: AsyncDisposable + Any + 'static>(&mut self) -> &mut Self {
+        let disposer: AsyncServiceDisposer = Rc::new(|service| {
+            Box::pin(async move {
+                if let Ok(service) = service.downcast::<T>() {
+                    service.dispose().await;
+                }
+            })
+        });
+
+        for descriptor in self.registrations_mut(TypeId::of::<T>(), std::any::type_name::<T>()) {
+            descriptor.async_disposer = Some(disposer.clone());
+        }
+
+        self
+    }
+
+    /// Same as [`ServiceCollection::mark_async_disposable`] for a concrete type
+    /// registered through the `_boxed` methods. `async fn` in traits can't be
+    /// called through `dyn`, so trait objects have to be disposed through the
+    /// concrete registration instead.
+    pub fn mark_async_disposable_boxed<T: AsyncDisposable + 'static>(&mut self) -> &mut Self {
+        let disposer: AsyncServiceDisposer = Rc::new(|service| {
+            Box::pin(async move {
+                if let Ok(service) = service.downcast::<Box<T>>() {
+                    service.dispose().await;
+                }
+            })
+        });
+
+        let type_name = std::any::type_name::<Box<T>>();
+        for descriptor in self.registrations_mut(TypeId::of::<Box<T>>(), type_name) {
+            descriptor.async_disposer = Some(disposer.clone());
+        }
+
+        self
+    }
+}
+
+impl ServiceProvider {
+    /// Async version of [`ServiceProvider::dispose_all`]: awaits the disposal of
+    /// every cached instance marked [`AsyncDisposable`] one after another, most
+    /// recently created first, and runs the synchronous disposers along the way.
+    pub async fn dispose_all_async(&self) {
+        for (descriptor, service) in self.take_cached_in_reverse() {
+            let Some(descriptor) = descriptor else {
+                continue;
+            };
+
+            if let Some(dispose) = &descriptor.async_disposer {
+                dispose(service.clone()).await;
+            }
+
+            if let Some(dispose) = &descriptor.disposer {
+                dispose(service.as_ref());
+            }
+        }
+    }
+
+    /// Creates a scope whose scoped instances are disposed asynchronously by
+    /// [`AsyncScope::close`].
+    pub fn create_async_scope(self: &Rc<Self>) -> AsyncScope {
+        AsyncScope {
+            scope: self.create_scope(),
+            closed: false,
+        }
+    }
+}
+
+/// A scope for services that need [`AsyncDisposable`] teardown. `Drop` can't
+/// await, so the scope has to be closed explicitly with `close().await`;
+/// dropping it unclosed skips the async disposers (and logs a warning with the
+/// `log` feature).
+///
+/// Resolves like the [`ScopedServiceProvider`] it wraps.
+#[derive(Debug)]
+pub struct AsyncScope {
+    scope: ScopedServiceProvider,
+    closed: bool,
+}
+
+impl AsyncScope {
+    /// Awaits the disposal of every instance cached by this scope, most
+    /// recently created first, running the synchronous disposers along the
+    /// way, then drops the scope. Has to be called for the async disposers to
+    /// run at all.
+    pub async fn close(mut self) {
+        let order = self.scope.creation_order.take();
+        let mut services = self.scope.services.take();
+        let descriptors = &self.scope.provider.collection.services;
+
+        for type_id in order.iter().rev() {
+            let Some(service) = services.remove(type_id) else {
+                continue;
+            };
+
+            let Some(descriptor) = descriptors.get(type_id) else {
+                continue;
+            };
+
+            if let Some(dispose) = &descriptor.async_disposer {
+                dispose(service.clone()).await;
+            }
+
+            if let Some(dispose) = &descriptor.disposer {
+                dispose(service.as_ref());
+            }
+        }
+
+        self.closed = true;
+    }
+}
+
+impl Deref for AsyncScope {
+    type Target = ScopedServiceProvider;
+
+    fn deref(&self) -> &Self::Target {
+        &self.scope
+    }
+}
+
+#[cfg(feature = "log")]
+impl Drop for AsyncScope {
+    fn drop(&mut self) {
+        if !self.closed && !std::thread::panicking() {
+            log::warn!("AsyncScope dropped without close(), async disposers didn't run");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::AsyncDisposable;
+    use crate::{ServiceCollection, resolve_async::block_on};
+
+    struct Pool(&'static str, Rc<RefCell<Vec<&'static str>>>);
+    impl AsyncDisposable for Pool {
+        async fn dispose(&self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    struct Cache(Rc<RefCell<Vec<&'static str>>>);
+    impl AsyncDisposable for Cache {
+        async fn dispose(&self) {
+            self.0.borrow_mut().push("cache");
+        }
+    }
+
+    #[test]
+    fn closing_an_async_scope_disposes_its_instances_only() {
+        let disposed = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = disposed.clone();
+        collection
+            .add_scoped_boxed::<Pool, _>(move |_| Box::new(Pool("pool", log.clone())))
+            .mark_async_disposable_boxed::<Pool>();
+        let log = disposed.clone();
+        collection
+            .add_scoped::<Cache, _>(move |provider| {
+                provider.get_boxed::<Pool>().unwrap();
+                Box::new(Cache(log.clone()))
+            })
+            .mark_async_disposable::<Cache>();
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_async_scope();
+        scope.get::<Cache>().unwrap();
+        let unused = provider.create_async_scope();
+
+        block_on(scope.close());
+        block_on(unused.close());
+
+        assert_eq!(*disposed.borrow(), vec!["cache", "pool"]);
+    }
+
+    #[test]
+    fn dispose_all_async_awaits_in_reverse_creation_order() {
+        let disposed = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = disposed.clone();
+        collection
+            .add_singleton_boxed::<Pool, _>(move |_| Box::new(Pool("pool", log.clone())))
+            .mark_async_disposable_boxed::<Pool>();
+        let log = disposed.clone();
+        collection
+            .add_singleton::<Cache, _>(move |_| Box::new(Cache(log.clone())))
+            .mark_async_disposable::<Cache>();
+
+        let provider = collection.build();
+        provider.get_boxed::<Pool>().unwrap();
+        provider.get::<Cache>().unwrap();
+
+        block_on(provider.dispose_all_async());
+
+        assert_eq!(*disposed.borrow(), vec!["cache", "pool"]);
+    }
+}
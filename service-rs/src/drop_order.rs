@@ -0,0 +1,129 @@
+//! Deterministic drop order of cached instances, behind the `ordered-drop`
+//! feature.
+//!
+//! The instances cached by the root provider (ordered and set implementations
+//! included), and by each scope, are released when it drops in reverse
+//! creation order instead of in hash map order, so a service goes away before
+//! the dependencies it was built from. Instances still held by callers are
+//! dropped when their last `Rc` goes away.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::{ScopedServiceProvider, ServiceProvider};
+
+fn release_in_reverse(services: &mut HashMap<TypeId, Rc<dyn Any>>, order: &mut Vec<TypeId>) {
+    for type_id in order.drain(..).rev() {
+        services.remove(&type_id);
+    }
+}
+
+impl Drop for ServiceProvider {
+    fn drop(&mut self) {
+        // dropped front to back, most recently created first
+        drop(self.take_cached_in_reverse());
+    }
+}
+
+impl ScopedServiceProvider {
+    // child scopes and views share the cache, the last one to go releases it
+    pub(crate) fn release_cached(&mut self) {
+        if Rc::strong_count(&self.services) == 1 {
+            release_in_reverse(
+                &mut self.services.borrow_mut(),
+                &mut self.creation_order.borrow_mut(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::ServiceCollection;
+
+    type DropLog = Rc<RefCell<Vec<usize>>>;
+
+    struct Logged<const N: usize>(DropLog);
+
+    impl<const N: usize> Drop for Logged<N> {
+        fn drop(&mut self) {
+            self.0.borrow_mut().push(N);
+        }
+    }
+
+    fn add_logged<const N: usize>(collection: &mut ServiceCollection, log: &DropLog) {
+        let log = log.clone();
+        collection.add_singleton_boxed::<Logged<N>, _>(move |_| Box::new(Logged(log.clone())));
+    }
+
+    fn add_scoped<const N: usize>(collection: &mut ServiceCollection, log: &DropLog) {
+        let log = log.clone();
+        collection.add_scoped_boxed::<Logged<N>, _>(move |_| Box::new(Logged(log.clone())));
+    }
+
+    #[test]
+    fn provider_drop_releases_instances_in_reverse_creation_order() {
+        let log = DropLog::default();
+        let mut collection = ServiceCollection::new();
+        add_logged::<1>(&mut collection, &log);
+        add_logged::<2>(&mut collection, &log);
+        add_logged::<3>(&mut collection, &log);
+
+        let provider = collection.build();
+        provider.get_boxed::<Logged<1>>().unwrap();
+        provider.get_boxed::<Logged<2>>().unwrap();
+        provider.get_boxed::<Logged<3>>().unwrap();
+        drop(provider);
+
+        assert_eq!(*log.borrow(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn scope_drop_releases_instances_in_reverse_creation_order() {
+        let log = DropLog::default();
+        let mut collection = ServiceCollection::new();
+        for add in [add_scoped::<1>, add_scoped::<2>, add_scoped::<3>] {
+            add(&mut collection, &log);
+        }
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.get_boxed::<Logged<1>>().unwrap();
+        scope.get_boxed::<Logged<2>>().unwrap();
+        scope.get_boxed::<Logged<3>>().unwrap();
+        drop(scope);
+
+        assert_eq!(*log.borrow(), vec![3, 2, 1]);
+    }
+
+    trait Member {}
+    impl<const N: usize> Member for Logged<N> {}
+
+    #[test]
+    fn provider_drop_releases_ordered_implementations_in_order_too() {
+        let log = DropLog::default();
+        let mut collection = ServiceCollection::new();
+        add_logged::<1>(&mut collection, &log);
+        let first = log.clone();
+        let second = log.clone();
+        collection
+            .add_singleton_ordered_boxed::<dyn Member, _>(0, move |_| {
+                Box::new(Logged::<2>(first.clone()))
+            })
+            .add_singleton_ordered_boxed::<dyn Member, _>(1, move |_| {
+                Box::new(Logged::<3>(second.clone()))
+            });
+
+        let provider = collection.build();
+        provider.get_boxed::<Logged<1>>().unwrap();
+        provider.get_all_boxed::<dyn Member>().unwrap();
+        drop(provider);
+
+        assert_eq!(*log.borrow(), vec![3, 2, 1]);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::{Lifetime, ServiceCollection, ServiceProvider};
+
+// variables whose name or value isn't valid unicode are left out
+pub(crate) fn snapshot() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+impl ServiceCollection {
+    /// Registers `T` as a singleton parsed from the environment. `build` reads
+    /// the environment once and runs `parse` right away, so every resolution
+    /// sees the same value however the environment changes later.
+    ///
+    /// Tests can hand `parse` a fake environment through
+    /// [`ServiceCollection::build_with_env`].
+    pub fn add_singleton_from_env<T: Any>(
+        &mut self,
+        parse: impl Fn(&HashMap<String, String>) -> T + 'static,
+    ) -> &mut Self {
+        let type_id = TypeId::of::<T>();
+        let type_name = std::any::type_name::<T>();
+
+        self.env_singletons.push((type_id, type_name));
+        self.register(
+            type_id,
+            type_name,
+            Lifetime::Singleton,
+            Rc::new(move |provider| Ok(Box::new(parse(&provider.env)) as Box<dyn Any>)),
+        )
+    }
+}
+
+impl ServiceProvider {
+    pub(crate) fn build_env_singletons(&self) {
+        for (type_id, type_name) in &self.collection.env_singletons {
+            // `parse` doesn't resolve anything, only a replaced registration
+            // can fail, and that one is reported again when it's resolved
+            let _ = self.resolve_any(*type_id, type_name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    use crate::ServiceCollection;
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        port: u16,
+    }
+
+    #[test]
+    fn env_singletons_are_parsed_once_at_build() {
+        let parses = Rc::new(Cell::new(0));
+
+        let mut collection = ServiceCollection::new();
+        let count = parses.clone();
+        collection.add_singleton_from_env(move |env| {
+            count.set(count.get() + 1);
+            Config {
+                port: env
+                    .get("PORT")
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(80),
+            }
+        });
+
+        let env = HashMap::from([("PORT".to_string(), "8080".to_string())]);
+        let provider = collection.build_with_env(env);
+
+        assert_eq!(parses.get(), 1);
+        assert_eq!(*provider.get::<Config>().unwrap(), Config { port: 8080 });
+        assert_eq!(parses.get(), 1);
+    }
+}
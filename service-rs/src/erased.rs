@@ -0,0 +1,165 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::{Error, ServiceProvider};
+
+impl ServiceProvider {
+    /// Resolves a service by its registered type name, e.g.
+    /// `"alloc::boxed::Box<dyn app::Logger>"`.
+    ///
+    /// An exact match wins, otherwise `name` may be a suffix of exactly one
+    /// registered type name (`"app::Logger>"`).
+    pub fn get_by_name(&self, name: &str) -> Result<Rc<dyn Any>, Error> {
+        let services = &self.collection.services;
+
+        let exact = services
+            .iter()
+            .find(|(_, descriptor)| descriptor.type_name == name);
+
+        let (type_id, descriptor) = match exact {
+            Some(entry) => entry,
+            None => {
+                let mut candidates = services
+                    .iter()
+                    .filter(|(_, descriptor)| descriptor.type_name.ends_with(name));
+
+                match (candidates.next(), candidates.next()) {
+                    (Some(entry), None) => entry,
+                    (Some(_), Some(_)) => return Err(Error::AmbiguousService(name.to_string())),
+                    _ => return Err(Error::ServiceNotFound(name.to_string())),
+                }
+            }
+        };
+
+        self.resolve_any(*type_id, descriptor.type_name)
+    }
+
+    /// Resolves every id in `ids`, keeping the outcome of each one. A failure
+    /// doesn't stop the others from being resolved.
+    pub fn get_many(&self, ids: &[TypeId]) -> HashMap<TypeId, Result<Rc<dyn Any>, Error>> {
+        ids.iter()
+            .map(|type_id| {
+                let service = match self.collection.services.get(type_id) {
+                    Some(descriptor) => self.resolve_any(*type_id, descriptor.type_name),
+                    None => Err(Error::ServiceNotFound(format!("{:?}", type_id))),
+                };
+
+                (*type_id, service)
+            })
+            .collect()
+    }
+
+    /// Resolves `types` strictly left to right, for singletons whose
+    /// construction has side effects that must happen in a given order (the
+    /// logger set up before the metrics, ...).
+    ///
+    /// Stops at the first failure, reported as
+    /// [`Error::OrderedResolutionFailed`] with the index of the failing id;
+    /// everything before it stays resolved.
+    pub fn resolve_in_order(&self, types: &[TypeId]) -> Result<Vec<Rc<dyn Any>>, Error> {
+        types
+            .iter()
+            .enumerate()
+            .map(|(index, type_id)| {
+                let service = match self.collection.services.get(type_id) {
+                    Some(descriptor) => self.resolve_any(*type_id, descriptor.type_name),
+                    None => Err(Error::ServiceNotFound(format!("{:?}", type_id))),
+                };
+
+                service.map_err(|error| Error::OrderedResolutionFailed {
+                    index,
+                    error: Box::new(error),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, cell::RefCell, rc::Rc};
+
+    use crate::{Error, ServiceCollection};
+
+    trait Logger {}
+    struct LoggerImpl;
+    impl Logger for LoggerImpl {}
+
+    #[test]
+    fn get_by_name_prefers_exact_then_unique_suffix() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Logger, _>(|_| Box::new(LoggerImpl));
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(7));
+        collection.add_singleton_boxed::<i32, _>(|_| Box::new(-7));
+
+        let provider = collection.build();
+
+        let logger = provider
+            .get_by_name(std::any::type_name::<Box<dyn Logger>>())
+            .unwrap();
+        assert!(logger.is::<Box<dyn Logger>>());
+
+        let number = provider.get_by_name("u32>").unwrap();
+        assert_eq!(**number.downcast::<Box<u32>>().unwrap(), 7);
+
+        assert!(matches!(
+            provider.get_by_name("32>"),
+            Err(Error::AmbiguousService(_))
+        ));
+        assert!(matches!(
+            provider.get_by_name("Missing"),
+            Err(Error::ServiceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn get_many_reports_each_id_separately() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(7));
+
+        let provider = collection.build();
+        let ids = [TypeId::of::<Box<u32>>(), TypeId::of::<Box<u64>>()];
+        let services = provider.get_many(&ids);
+
+        assert!(services[&ids[0]].is_ok());
+        assert!(matches!(services[&ids[1]], Err(Error::ServiceNotFound(_))));
+    }
+
+    #[test]
+    fn resolve_in_order_follows_the_given_order_and_stops_at_failures() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+        let logger_log = log.clone();
+        collection.add_singleton_boxed::<dyn Logger, _>(move |_| {
+            logger_log.borrow_mut().push("logger");
+            Box::new(LoggerImpl)
+        });
+        let metrics_log = log.clone();
+        collection.add_singleton_boxed::<u32, _>(move |_| {
+            metrics_log.borrow_mut().push("metrics");
+            Box::new(7)
+        });
+
+        let provider = collection.build();
+        let services = provider
+            .resolve_in_order(&[TypeId::of::<Box<u32>>(), TypeId::of::<Box<dyn Logger>>()])
+            .unwrap();
+
+        assert_eq!(services.len(), 2);
+        assert!(services[0].is::<Box<u32>>());
+        assert_eq!(*log.borrow(), vec!["metrics", "logger"]);
+
+        let Err(Error::OrderedResolutionFailed { index, error }) = provider.resolve_in_order(&[
+            TypeId::of::<Box<u32>>(),
+            TypeId::of::<Box<u64>>(),
+            TypeId::of::<Box<dyn Logger>>(),
+        ]) else {
+            panic!("expected the missing service to fail");
+        };
+        assert_eq!(index, 1);
+        assert!(matches!(*error, Error::ServiceNotFound(_)));
+    }
+}
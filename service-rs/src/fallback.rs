@@ -0,0 +1,97 @@
+use crate::{Error, ServiceProvider};
+
+/// One attempt of [`ServiceProvider::get_first_available_boxed`], resolving a
+/// service and adapting it to the common type.
+pub type FallbackAttempt<'a, T> = &'a dyn Fn(&ServiceProvider) -> Result<Box<T>, Error>;
+
+impl ServiceProvider {
+    /// Runs `attempts` in order and returns the first one whose service is
+    /// registered. Only a missing service moves on to the next attempt, any
+    /// other error is returned as-is. Usually called through
+    /// [`try_resolve!`](crate::try_resolve), which builds the attempts.
+    ///
+    /// Fails with [`Error::ServiceNotFound`] naming every service tried if
+    /// none of them is registered.
+    pub fn get_first_available_boxed<T: ?Sized + 'static>(
+        &self,
+        attempts: &[FallbackAttempt<'_, T>],
+    ) -> Result<Box<T>, Error> {
+        let mut missing = Vec::new();
+
+        for attempt in attempts {
+            match attempt(self) {
+                Err(Error::ServiceNotFound(service_name)) => missing.push(service_name),
+                result => return result,
+            }
+        }
+
+        Err(Error::ServiceNotFound(missing.join(" or ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{Error, ServiceCollection, try_resolve};
+
+    trait Cache {
+        fn name(&self) -> String;
+    }
+
+    trait PrimaryCache {
+        fn primary_name(&self) -> &'static str;
+    }
+
+    trait SecondaryCache {
+        fn secondary_name(&self) -> &'static str;
+    }
+
+    struct Redis;
+    impl SecondaryCache for Redis {
+        fn secondary_name(&self) -> &'static str {
+            "redis"
+        }
+    }
+
+    struct Primary(Rc<Box<dyn PrimaryCache>>);
+    impl Cache for Primary {
+        fn name(&self) -> String {
+            self.0.primary_name().to_string()
+        }
+    }
+
+    struct Secondary(Rc<Box<dyn SecondaryCache>>);
+    impl Cache for Secondary {
+        fn name(&self) -> String {
+            format!("{} (fallback)", self.0.secondary_name())
+        }
+    }
+
+    #[test]
+    fn try_resolve_falls_back_to_the_next_registered_service() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn SecondaryCache, _>(|_| Box::new(Redis));
+
+        let provider = collection.build();
+        let cache: Box<dyn Cache> = try_resolve!(
+            provider => dyn Cache,
+            dyn PrimaryCache => |cache| Box::new(Primary(cache)),
+            dyn SecondaryCache => |cache| Box::new(Secondary(cache)),
+        )
+        .unwrap();
+
+        assert_eq!(cache.name(), "redis (fallback)");
+
+        let empty = ServiceCollection::new().build();
+        let missing: Result<Box<dyn Cache>, Error> = try_resolve!(
+            empty => dyn Cache,
+            dyn PrimaryCache => |cache| Box::new(Primary(cache)),
+            dyn SecondaryCache => |cache| Box::new(Secondary(cache)),
+        );
+        let Err(Error::ServiceNotFound(message)) = missing else {
+            panic!("expected a missing service");
+        };
+        assert!(message.contains("PrimaryCache") && message.contains("SecondaryCache"));
+    }
+}
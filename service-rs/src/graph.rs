@@ -0,0 +1,356 @@
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::unwind::OnUnwind;
+use crate::{Error, Lifetime, RootCaches, ServiceCollection, ServiceProvider};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    pub(crate) edges: Vec<(&'static str, &'static str)>,
+}
+
+impl DependencyGraph {
+    /// `(dependent, dependency)` pairs, sorted by type name.
+    pub fn edges(&self) -> &[(&'static str, &'static str)] {
+        &self.edges
+    }
+
+    // dependencies of every dependent, in edge order
+    fn adjacency(&self) -> HashMap<&'static str, Vec<&'static str>> {
+        let mut dependencies: HashMap<&'static str, Vec<&'static str>> = HashMap::new();
+        for (dependent, dependency) in &self.edges {
+            dependencies.entry(dependent).or_default().push(dependency);
+        }
+        dependencies
+    }
+}
+
+impl ServiceCollection {
+    /// Fails with [`Error::DependencyCycle`], naming the whole cycle, if any
+    /// registered services depend on each other in a loop. Meant for a test in
+    /// CI, so cycles show up before the first resolution in production does.
+    ///
+    /// The edges come from [`ServiceProvider::trace_graph`] on a throwaway
+    /// provider: every factory runs once, nothing is cached anywhere.
+    pub fn assert_acyclic(&self) -> Result<(), Error> {
+        let graph = self.clone().build().trace_graph();
+        let dependencies = graph.adjacency();
+        let mut visited = BTreeSet::new();
+
+        for (dependent, _) in graph.edges() {
+            visit(
+                dependent,
+                &dependencies,
+                &mut Vec::new(),
+                &mut visited,
+                &mut Vec::new(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ServiceProvider {
+    // called on every `get`, while a trace is running and a factory is active
+    // the requested type is recorded as a dependency of that factory's type.
+    pub(crate) fn record_dependency(&self, type_name: &'static str) {
+        if let Some(edges) = self.trace.borrow_mut().as_mut()
+            && let Some((_, parent, _)) = self.stack.borrow().last()
+        {
+            edges.insert((*parent, type_name));
+        }
+    }
+
+    /// Builds the dependency graph from the actual runtime wiring by running the
+    /// factory of every registered service once, in type name order.
+    ///
+    /// The instances are created against a throwaway cache which is discarded
+    /// afterwards, so the provider's cached singletons are left untouched. Factory
+    /// side effects still happen, and failing factories only contribute the edges
+    /// recorded before they failed.
+    pub fn trace_graph(&self) -> DependencyGraph {
+        let mut cached = Some(self.replace_root_caches(RootCaches::default()));
+        *self.trace.borrow_mut() = Some(BTreeSet::new());
+        let guard = OnUnwind(|| {
+            *self.trace.borrow_mut() = None;
+            self.replace_root_caches(cached.take().unwrap_or_default());
+        });
+
+        // sorted so factory side effects happen in the same order on every run
+        let mut services = self.collection.services.iter().collect::<Vec<_>>();
+        services.sort_unstable_by_key(|(_, descriptor)| descriptor.type_name);
+
+        for (type_id, descriptor) in services {
+            let _ = self.create(type_id, descriptor.type_name);
+        }
+
+        guard.disarm();
+        let edges = self.trace.borrow_mut().take().unwrap_or_default();
+        self.replace_root_caches(cached.unwrap_or_default());
+
+        DependencyGraph {
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// Constructs the listed singletons, and the singletons they depend on, in
+    /// dependency order: every dependency is built before its dependents, no
+    /// matter the order in which factories ask for them.
+    ///
+    /// The order comes from [`ServiceProvider::trace_graph`], so every factory
+    /// runs once against a throwaway cache first. A cycle is reported as
+    /// [`Error::DependencyCycle`] and nothing gets constructed.
+    pub fn prepare(&self, types: &[TypeId]) -> Result<(), Vec<Error>> {
+        let dependencies = self.trace_graph().adjacency();
+
+        let mut errors = Vec::new();
+        let mut order = Vec::new();
+        let mut visited = BTreeSet::new();
+
+        for type_id in types {
+            match self.collection.services.get(type_id) {
+                Some(descriptor) => {
+                    if let Err(error) = visit(
+                        descriptor.type_name,
+                        &dependencies,
+                        &mut Vec::new(),
+                        &mut visited,
+                        &mut order,
+                    ) {
+                        errors.push(error);
+                    }
+                }
+                None => errors.push(Error::ServiceNotFound(format!("{:?}", type_id))),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let type_ids: HashMap<&'static str, TypeId> = self
+            .collection
+            .services
+            .iter()
+            .map(|(type_id, descriptor)| (descriptor.type_name, *type_id))
+            .collect();
+
+        for type_name in order {
+            let Some(type_id) = type_ids.get(type_name) else {
+                continue;
+            };
+
+            if self.collection.services[type_id].lifetime != Lifetime::Singleton {
+                continue;
+            }
+
+            if let Err(error) = self.resolve_any(*type_id, type_name) {
+                errors.push(error);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// depth-first post-order walk, `path` holds the types currently being visited
+fn visit(
+    type_name: &'static str,
+    dependencies: &HashMap<&'static str, Vec<&'static str>>,
+    path: &mut Vec<&'static str>,
+    visited: &mut BTreeSet<&'static str>,
+    order: &mut Vec<&'static str>,
+) -> Result<(), Error> {
+    if visited.contains(type_name) {
+        return Ok(());
+    }
+
+    if let Some(start) = path.iter().position(|name| *name == type_name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(type_name);
+        return Err(Error::DependencyCycle(cycle.join(" -> ")));
+    }
+
+    path.push(type_name);
+    for dependency in dependencies.get(type_name).into_iter().flatten() {
+        visit(dependency, dependencies, path, visited, order)?;
+    }
+    path.pop();
+
+    visited.insert(type_name);
+    order.push(type_name);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::{Error, ServiceCollection, ServiceProvider};
+
+    trait Repository {}
+    struct RepositoryImpl;
+    impl Repository for RepositoryImpl {}
+
+    trait Handler {}
+    #[allow(dead_code)]
+    struct HandlerImpl(Rc<Box<dyn Repository>>);
+    impl Handler for HandlerImpl {}
+
+    #[test]
+    fn trace_graph_records_runtime_edges_without_touching_cache() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Repository, _>(|_| Box::new(RepositoryImpl));
+        collection.add_transient_boxed::<dyn Handler, _>(|provider| {
+            Box::new(HandlerImpl(provider.get_boxed::<dyn Repository>().unwrap()))
+        });
+
+        let provider = collection.build();
+        let repository = provider.get_boxed::<dyn Repository>().unwrap();
+
+        let graph = provider.trace_graph();
+
+        assert_eq!(
+            graph.edges(),
+            &[(
+                std::any::type_name::<Box<dyn Handler>>(),
+                std::any::type_name::<Box<dyn Repository>>()
+            )]
+        );
+        assert!(Rc::ptr_eq(
+            &repository,
+            &provider.get_boxed::<dyn Repository>().unwrap()
+        ));
+    }
+
+    fn logged<T: Default + 'static>(
+        log: &Rc<RefCell<Vec<&'static str>>>,
+    ) -> impl Fn(&ServiceProvider) -> Box<T> + 'static {
+        let log = log.clone();
+        move |_| {
+            log.borrow_mut().push(std::any::type_name::<Box<T>>());
+            Box::default()
+        }
+    }
+
+    #[test]
+    fn trace_graph_runs_factories_in_type_name_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_boxed::<u8, _>(logged(&log))
+            .add_transient_boxed::<u64, _>(logged(&log))
+            .add_transient_boxed::<i16, _>(logged(&log))
+            .add_transient_boxed::<u32, _>(logged(&log));
+
+        collection.build().trace_graph();
+
+        let log = log.borrow();
+        assert_eq!(log.len(), 4);
+        assert!(log.is_sorted());
+    }
+
+    #[test]
+    fn prepare_builds_dependencies_before_dependents() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+
+        let handler_log = log.clone();
+        collection.add_singleton_boxed::<dyn Handler, _>(move |provider| {
+            handler_log.borrow_mut().push("handler");
+            Box::new(HandlerImpl(provider.get_boxed::<dyn Repository>().unwrap()))
+        });
+        let repository_log = log.clone();
+        collection.add_singleton_boxed::<dyn Repository, _>(move |_| {
+            repository_log.borrow_mut().push("repository");
+            Box::new(RepositoryImpl)
+        });
+
+        let provider = collection.build();
+        provider
+            .prepare(&[TypeId::of::<Box<dyn Handler>>()])
+            .unwrap();
+
+        assert!(log.borrow().ends_with(&["repository", "handler"]));
+        assert_eq!(provider.cached_instances(), 2);
+    }
+
+    #[test]
+    fn assert_acyclic_reports_the_full_cycle() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_boxed::<u8, _>(|provider| {
+                Box::new(provider.get_boxed::<u16>().map_or(0, |value| **value as u8))
+            })
+            .add_transient_boxed::<u16, _>(|provider| {
+                Box::new(
+                    provider
+                        .get_boxed::<u32>()
+                        .map_or(0, |value| **value as u16),
+                )
+            })
+            .add_singleton_boxed::<u32, _>(|provider| {
+                Box::new(provider.get_boxed::<u8>().map_or(0, |value| **value as u32))
+            });
+
+        let Err(Error::DependencyCycle(path)) = collection.assert_acyclic() else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(path.matches(" -> ").count(), 3);
+
+        let mut acyclic = ServiceCollection::new();
+        acyclic
+            .add_singleton_boxed::<dyn Repository, _>(|_| Box::new(RepositoryImpl))
+            .add_transient_boxed::<dyn Handler, _>(|provider| {
+                Box::new(HandlerImpl(provider.get_boxed::<dyn Repository>().unwrap()))
+            });
+        acyclic.assert_acyclic().unwrap();
+    }
+
+    #[test]
+    fn prepare_reports_cycles_without_constructing() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|provider| {
+            Box::new(
+                provider
+                    .get_boxed::<u64>()
+                    .map_or(0, |value| **value as u32),
+            )
+        });
+        collection.add_singleton_boxed::<u64, _>(|provider| {
+            Box::new(
+                provider
+                    .get_boxed::<u32>()
+                    .map_or(0, |value| **value as u64),
+            )
+        });
+
+        let provider = collection.build();
+        let errors = provider.prepare(&[TypeId::of::<Box<u32>>()]).unwrap_err();
+
+        assert!(matches!(errors.as_slice(), [Error::DependencyCycle(_)]));
+        assert_eq!(provider.cached_instances(), 0);
+    }
+
+    #[test]
+    fn tracing_leaves_enumerable_singletons_uncached() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_ordered_boxed::<dyn Repository, _>(0, |_| Box::new(RepositoryImpl))
+            .add_collection_boxed::<dyn Repository>();
+
+        let provider = collection.build();
+        provider.trace_graph();
+
+        assert_eq!(provider.cached_instances(), 0);
+        assert!(provider.creation_order.borrow().is_empty());
+    }
+}
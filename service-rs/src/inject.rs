@@ -0,0 +1,477 @@
+use std::{
+    any::{Any, TypeId},
+    cell::OnceCell,
+    rc::{Rc, Weak},
+};
+
+use crate::unwind::OnUnwind;
+use crate::{
+    Error, Lifetime, ScopedServiceProvider, ServiceCollection, ServiceDescriptor, ServiceProvider,
+    SharedInstance,
+};
+
+type InjectionFn = Box<dyn FnOnce(&ServiceProvider) -> Result<(), Error>>;
+
+// binds the `Injected` fields of one instance built during the current resolution
+pub(crate) struct Injection(InjectionFn);
+
+impl std::fmt::Debug for Injection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Injection")
+    }
+}
+
+/// A dependency bound after its owner was constructed, see
+/// [`ServiceCollection::add_scoped_injectable`].
+pub struct Injected<T: ?Sized + 'static>(OnceCell<Rc<Box<T>>>);
+
+impl<T: ?Sized + Any + 'static> Injected<T> {
+    pub fn new() -> Self {
+        Injected(OnceCell::new())
+    }
+
+    /// Resolves `Box<T>` from `provider` and binds it, unless already bound.
+    pub fn resolve(&self, provider: &ServiceProvider) -> Result<(), Error> {
+        if self.0.get().is_none() {
+            let _ = self.0.set(provider.get_boxed::<T>()?);
+        }
+
+        Ok(())
+    }
+
+    /// The bound dependency, `None` until the owner has been injected.
+    pub fn get(&self) -> Option<&Rc<Box<T>>> {
+        self.0.get()
+    }
+}
+
+impl<T: ?Sized + Any + 'static> Default for Injected<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + 'static> std::fmt::Debug for Injected<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Injected")
+            .field(&self.0.get().is_some())
+            .finish()
+    }
+}
+
+/// A back-reference bound after its owner was constructed, like [`Injected`]
+/// but holding a `Weak`, so a service pointing back at the aggregate that owns
+/// it doesn't form an `Rc` cycle.
+///
+/// The reference has to be upgraded on every use, and the upgrade fails once
+/// the cache that held the instance (the scope, or the provider) is gone.
+pub struct WeakInject<T: ?Sized + 'static>(OnceCell<Weak<Box<T>>>);
+
+impl<T: ?Sized + Any + 'static> WeakInject<T> {
+    pub fn new() -> Self {
+        WeakInject(OnceCell::new())
+    }
+
+    /// Binds a weak reference to the cached `Box<T>`, unless already bound.
+    pub fn resolve(&self, provider: &ServiceProvider) -> Result<(), Error> {
+        if self.0.get().is_none() {
+            let _ = self.0.set(provider.get_weak_boxed::<T>()?);
+        }
+
+        Ok(())
+    }
+
+    /// The referenced instance, `None` until bound or once it was dropped.
+    pub fn get(&self) -> Option<Rc<Box<T>>> {
+        self.0.get()?.upgrade()
+    }
+}
+
+impl<T: ?Sized + Any + 'static> Default for WeakInject<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized + 'static> std::fmt::Debug for WeakInject<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WeakInject")
+            .field(&self.0.get().is_some())
+            .finish()
+    }
+}
+
+/// Binds the [`Injected`] fields of a service, usually by calling
+/// [`Injected::resolve`] on each of them.
+pub trait Inject {
+    fn inject(&self, provider: &ServiceProvider) -> Result<(), Error>;
+}
+
+/// A struct built outside of the container (deserialized, created by a
+/// framework, ...) whose dependencies are filled in afterwards through
+/// [`ServiceProvider::inject_fields`]. Usually implemented with
+/// [`inject_target!`](crate::inject_target).
+pub trait InjectTarget {
+    fn inject_fields(&mut self, provider: &ServiceProvider) -> Result<(), Error>;
+}
+
+impl ServiceCollection {
+    /// Registers a scoped `T` whose [`Injected`] fields are bound after the
+    /// outermost resolution that built it has finished, in the scope it was
+    /// built in. By then `T` is cached, so its dependencies may resolve `T`
+    /// back (two services holding each other this way never get dropped).
+    ///
+    /// If the injection fails, that resolution fails with its error and `T`
+    /// is dropped from the scope again, the next resolution builds it anew.
+    /// Instances dropped because the resolution that built them failed aren't
+    /// injected.
+    pub fn add_scoped_injectable<T: Inject + Any + 'static>(
+        &mut self,
+        factory: impl Fn(&ServiceProvider) -> T + 'static,
+    ) -> &mut Self {
+        self.register(
+            std::any::TypeId::of::<T>(),
+            std::any::type_name::<T>(),
+            Lifetime::Scoped,
+            Rc::new(move |provider| {
+                let service = Rc::new(factory(provider));
+                let scope = provider.scopes.borrow().last().cloned();
+
+                let pending = service.clone();
+                provider.injections.borrow_mut().push(Injection(Box::new(
+                    move |provider: &ServiceProvider| {
+                        let Some(scope) = scope else {
+                            return pending.inject(provider);
+                        };
+
+                        // rolled back along with a failed scoped resolution
+                        let cached = scope.services.borrow().get(&TypeId::of::<T>()).cloned();
+                        if !cached.is_some_and(|cached| {
+                            std::ptr::addr_eq(Rc::as_ptr(&cached), Rc::as_ptr(&pending))
+                        }) {
+                            return Ok(());
+                        }
+
+                        provider.scopes.borrow_mut().push(scope.clone());
+                        let guard = OnUnwind(|| {
+                            provider.scopes.borrow_mut().pop();
+                        });
+                        let injected = pending.inject(provider);
+                        guard.disarm();
+                        provider.scopes.borrow_mut().pop();
+
+                        // don't hand out a half-bound instance from the cache
+                        if injected.is_err() {
+                            let type_id = TypeId::of::<T>();
+                            scope.services.borrow_mut().remove(&type_id);
+                            scope
+                                .creation_order
+                                .borrow_mut()
+                                .retain(|cached| *cached != type_id);
+                        }
+
+                        injected
+                    },
+                )));
+
+                Ok(Box::new(SharedInstance(service)) as Box<dyn Any>)
+            }),
+        )
+    }
+}
+
+impl ServiceProvider {
+    /// Resolves `Box<T>` and returns a weak reference to the cached instance,
+    /// see [`WeakInject`]. Fails with [`Error::NotCached`] for services built
+    /// fresh here, which nothing would keep alive: transients, and scoped
+    /// services outside of a scope.
+    pub fn get_weak_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Weak<Box<T>>, Error> {
+        let type_name = std::any::type_name::<Box<T>>();
+        let descriptor = self.descriptor(&TypeId::of::<Box<T>>(), type_name)?;
+
+        if !kept_alive(descriptor, !self.scopes.borrow().is_empty()) {
+            return Err(Error::NotCached(type_name.to_string()));
+        }
+
+        Ok(Rc::downgrade(&self.get_boxed::<T>()?))
+    }
+
+    /// Fills in the injectable fields of `target`, see [`InjectTarget`].
+    pub fn inject_fields<T: InjectTarget>(&self, target: &mut T) -> Result<(), Error> {
+        target.inject_fields(self)
+    }
+
+    /// Resolves `Box<T>` into `field` if it's still `None`, fields already set
+    /// are left untouched.
+    pub fn inject_field<T: ?Sized + Any + 'static>(
+        &self,
+        field: &mut Option<Rc<Box<T>>>,
+    ) -> Result<(), Error> {
+        if field.is_none() {
+            *field = Some(self.get_boxed::<T>()?);
+        }
+
+        Ok(())
+    }
+
+    // runs the injections queued while resolving, once no factory is running.
+    // all of them run, the first failure is reported
+    pub(crate) fn run_injections(&self) -> Result<(), Error> {
+        if !self.stack.borrow().is_empty() {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+
+        // a panicking injection leaves the rest of them behind, bound to
+        // instances of a resolution that never finished
+        let guard = OnUnwind(|| self.injections.borrow_mut().clear());
+        loop {
+            let Some(injection) = self.injections.borrow_mut().pop() else {
+                break;
+            };
+            let injected = injection.0(self);
+            result = result.and(injected);
+        }
+        guard.disarm();
+
+        result
+    }
+}
+
+impl ScopedServiceProvider {
+    /// Same as [`ServiceProvider::get_weak_boxed`], resolving scoped services
+    /// in this scope.
+    pub fn get_weak_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Weak<Box<T>>, Error> {
+        let type_name = std::any::type_name::<Box<T>>();
+        let descriptor = self
+            .provider
+            .descriptor(&TypeId::of::<Box<T>>(), type_name)?;
+
+        if !kept_alive(descriptor, true) {
+            return Err(Error::NotCached(type_name.to_string()));
+        }
+
+        Ok(Rc::downgrade(&self.get_boxed::<T>()?))
+    }
+}
+
+// whether resolving `descriptor` caches the instance, given whether a scope is
+// there to cache it
+fn kept_alive(descriptor: &ServiceDescriptor, in_scope: bool) -> bool {
+    match descriptor.lifetime {
+        Lifetime::Singleton => true,
+        Lifetime::Scoped => in_scope,
+        Lifetime::Transient => in_scope && descriptor.cache_in_scope,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::{Inject, Injected, WeakInject};
+    use crate::{Error, ServiceCollection, ServiceProvider};
+
+    trait FirstDep {
+        fn name(&self) -> &'static str;
+    }
+
+    struct FirstDepImpl;
+    impl FirstDep for FirstDepImpl {
+        fn name(&self) -> &'static str {
+            "first"
+        }
+    }
+
+    #[derive(Default)]
+    struct ThirdDepImpl {
+        first: Injected<dyn FirstDep>,
+    }
+
+    impl Inject for ThirdDepImpl {
+        fn inject(&self, provider: &ServiceProvider) -> Result<(), Error> {
+            self.first.resolve(provider)
+        }
+    }
+
+    #[derive(Default)]
+    struct Handler {
+        first: Option<Rc<Box<dyn FirstDep>>>,
+        fallback: Option<Rc<Box<dyn FirstDep>>>,
+    }
+
+    crate::inject_target!(Handler { first: dyn FirstDep, fallback: dyn FirstDep });
+
+    struct FallbackDep;
+    impl FirstDep for FallbackDep {
+        fn name(&self) -> &'static str {
+            "fallback"
+        }
+    }
+
+    #[test]
+    fn inject_fields_fills_only_unset_fields() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn FirstDep, _>(|_| Box::new(FirstDepImpl));
+
+        let provider = collection.build();
+        let mut handler = Handler {
+            fallback: Some(Rc::new(Box::new(FallbackDep))),
+            ..Default::default()
+        };
+        provider.inject_fields(&mut handler).unwrap();
+
+        assert_eq!(handler.first.unwrap().name(), "first");
+        assert_eq!(handler.fallback.unwrap().name(), "fallback");
+    }
+
+    struct Order(&'static str);
+
+    trait OrderLine {
+        fn order_name(&self) -> Option<&'static str>;
+    }
+
+    #[derive(Default)]
+    struct OrderLineImpl {
+        order: WeakInject<Order>,
+    }
+    impl OrderLine for OrderLineImpl {
+        fn order_name(&self) -> Option<&'static str> {
+            self.order.get().map(|order| order.0)
+        }
+    }
+
+    #[test]
+    fn weak_back_references_do_not_keep_the_scope_graph_alive() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<Order, _>(|_| Box::new(Order("order-1")))
+            .add_scoped_boxed::<dyn OrderLine, _>(|provider| {
+                let line = OrderLineImpl::default();
+                line.order.resolve(provider).unwrap();
+                Box::new(line)
+            })
+            .add_transient_boxed::<u32, _>(|_| Box::new(1));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let line = scope.get_boxed::<dyn OrderLine>().unwrap();
+
+        assert_eq!(line.order_name(), Some("order-1"));
+        assert!(matches!(
+            scope.get_weak_boxed::<u32>(),
+            Err(Error::NotCached(_))
+        ));
+
+        drop(scope);
+        assert_eq!(line.order_name(), None);
+    }
+
+    #[test]
+    fn weak_references_are_refused_for_scoped_services_outside_a_scope() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<Order, _>(|_| Box::new(Order("order-1")))
+            .add_transient_cached_in_scope_boxed::<u32, _>(|_| Box::new(1))
+            .add_singleton_boxed::<u64, _>(|_| Box::new(2));
+
+        let provider = Rc::new(collection.build());
+
+        assert!(matches!(
+            provider.get_weak_boxed::<Order>(),
+            Err(Error::NotCached(_))
+        ));
+        assert!(matches!(
+            provider.get_weak_boxed::<u32>(),
+            Err(Error::NotCached(_))
+        ));
+        assert!(
+            provider
+                .get_weak_boxed::<u64>()
+                .unwrap()
+                .upgrade()
+                .is_some()
+        );
+
+        let scope = provider.create_scope();
+        assert!(scope.get_weak_boxed::<Order>().unwrap().upgrade().is_some());
+        assert!(scope.get_weak_boxed::<u32>().unwrap().upgrade().is_some());
+    }
+
+    #[test]
+    fn injected_fields_are_bound_after_construction() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<dyn FirstDep, _>(|_| Box::new(FirstDepImpl))
+            .add_scoped_injectable(|_| ThirdDepImpl::default());
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let third = scope.get::<ThirdDepImpl>().unwrap();
+
+        let first = third.first.get().unwrap();
+        assert_eq!(first.name(), "first");
+        assert!(Rc::ptr_eq(
+            first,
+            &scope.get_boxed::<dyn FirstDep>().unwrap()
+        ));
+        assert!(Rc::ptr_eq(&third, &scope.get::<ThirdDepImpl>().unwrap()));
+    }
+
+    #[test]
+    fn failed_injections_fail_every_resolution() {
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_injectable(move |_| {
+            counter.set(counter.get() + 1);
+            ThirdDepImpl::default()
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                scope.get::<ThirdDepImpl>(),
+                Err(Error::ServiceNotFound(_))
+            ));
+        }
+        assert_eq!(built.get(), 2);
+        assert!(scope.services.borrow().is_empty());
+    }
+
+    struct Audited(Rc<Cell<u32>>);
+
+    impl Inject for Audited {
+        fn inject(&self, _: &ServiceProvider) -> Result<(), Error> {
+            self.0.set(self.0.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn instances_rolled_back_with_their_resolution_are_not_injected() {
+        let injected = Rc::new(Cell::new(0));
+        let counter = injected.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_injectable(move |_| Audited(counter.clone()))
+            .try_add_scoped_boxed::<Order, _>(|provider| {
+                provider.get::<Audited>()?;
+                Err(Error::Unknown("order rejected".to_string()))
+            });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+
+        assert!(scope.get_boxed::<Order>().is_err());
+        assert_eq!(injected.get(), 0);
+
+        scope.get::<Audited>().unwrap();
+        assert_eq!(injected.get(), 1);
+    }
+}
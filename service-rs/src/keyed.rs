@@ -0,0 +1,232 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    hash::Hash,
+    rc::Rc,
+};
+
+use crate::unwind::OnUnwind;
+use crate::{Error, Lifetime, ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+type KeyedFactoryFn = Rc<dyn Fn(&ServiceProvider, &dyn Any) -> Box<dyn Any>>;
+
+// a factory taking a runtime key, registered under the `(Box<T>, K)` type id
+#[derive(Clone)]
+pub(crate) struct KeyedFactory(pub(crate) KeyedFactoryFn);
+
+impl std::fmt::Debug for KeyedFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyedFactory")
+    }
+}
+
+impl ServiceCollection {
+    /// Registers a scoped `T` built from a runtime key, e.g. a tenant id. Each
+    /// scope caches one instance per distinct key, resolved through
+    /// [`ScopedServiceProvider::get_scoped_keyed_boxed`].
+    pub fn add_scoped_keyed_by_arg_boxed<T: ?Sized + 'static, K: Hash + Eq + Clone + 'static>(
+        &mut self,
+        factory: impl Fn(&ServiceProvider, &K) -> Box<T> + 'static,
+    ) -> &mut Self {
+        self.keyed.insert(
+            TypeId::of::<(Box<T>, K)>(),
+            KeyedFactory(Rc::new(move |provider, key| {
+                let key = key
+                    .downcast_ref::<K>()
+                    .expect("keyed factory called with another key type");
+                Box::new(factory(provider, key))
+            })),
+        );
+
+        self
+    }
+}
+
+impl ScopedServiceProvider {
+    /// Resolves the instance of `T` for `key`, running the factory the first
+    /// time the key is seen in this scope and reusing the instance afterwards.
+    pub fn get_scoped_keyed_boxed<T: ?Sized + 'static, K: Hash + Eq + Clone + 'static>(
+        &self,
+        key: K,
+    ) -> Result<Rc<Box<T>>, Error> {
+        let type_id = TypeId::of::<(Box<T>, K)>();
+        let type_name = std::any::type_name::<(Box<T>, K)>();
+        let provider = &self.provider;
+
+        let Some(factory) = provider.collection.keyed.get(&type_id) else {
+            return Err(Error::ServiceNotFound(type_name.to_string()));
+        };
+
+        let cached = self
+            .keyed
+            .borrow()
+            .get(&type_id)
+            .and_then(|instances| instances.downcast_ref::<HashMap<K, Rc<Box<T>>>>())
+            .and_then(|instances| instances.get(&key).cloned());
+
+        if let Some(service) = cached {
+            provider.record_hit(type_id);
+            return Ok(service);
+        }
+
+        provider.record_miss(type_id);
+        provider.enter_factory(type_id, type_name, Lifetime::Scoped)?;
+
+        // nested scoped services land in this scope
+        provider.scopes.borrow_mut().push(self.active());
+        let guard = OnUnwind(|| {
+            provider.scopes.borrow_mut().pop();
+            provider.abandon_factory();
+        });
+        let mut service = factory.0(provider, &key);
+        guard.disarm();
+        provider.scopes.borrow_mut().pop();
+        provider.finish_factory();
+
+        provider.run_post_constructs(type_name, &mut service);
+        provider.run_injections()?;
+
+        // a post-construct hook may have swapped the instance
+        let service =
+            Rc::new(
+                *service
+                    .downcast::<Box<T>>()
+                    .map_err(|_| Error::FactoryTypeMismatch {
+                        registered: type_name,
+                    })?,
+            );
+
+        self.keyed
+            .borrow_mut()
+            .entry(type_id)
+            .or_insert_with(|| Box::new(HashMap::<K, Rc<Box<T>>>::new()))
+            .downcast_mut::<HashMap<K, Rc<Box<T>>>>()
+            .expect("keyed cache holds another key type")
+            .insert(key, service.clone());
+
+        Ok(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{Error, ResolveStats, ServiceCollection};
+
+    trait Connection {
+        fn tenant(&self) -> &str;
+    }
+
+    struct TenantConnection(String);
+    impl Connection for TenantConnection {
+        fn tenant(&self) -> &str {
+            &self.0
+        }
+    }
+
+    #[test]
+    fn keyed_scoped_instances_are_cached_per_key_and_scope() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_keyed_by_arg_boxed::<dyn Connection, String>(|_, tenant| {
+            Box::new(TenantConnection(tenant.clone()))
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+
+        let acme = scope
+            .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string())
+            .unwrap();
+        let globex = scope
+            .get_scoped_keyed_boxed::<dyn Connection, _>("globex".to_string())
+            .unwrap();
+
+        assert_eq!(acme.tenant(), "acme");
+        assert_eq!(globex.tenant(), "globex");
+        assert!(Rc::ptr_eq(
+            &acme,
+            &scope
+                .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string())
+                .unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &acme,
+            &provider
+                .create_scope()
+                .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string())
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn keyed_factories_are_tracked_like_any_other_factory() {
+        let constructed = Rc::new(RefCell::new(Vec::new()));
+        let seen = constructed.clone();
+        let chains = Rc::new(RefCell::new(Vec::new()));
+        let chain = chains.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_post_construct(move |type_name, _| seen.borrow_mut().push(type_name))
+            .add_scoped_keyed_by_arg_boxed::<dyn Connection, String>(move |provider, tenant| {
+                chain.borrow_mut().push(provider.current_chain());
+                Box::new(TenantConnection(tenant.clone()))
+            });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        for _ in 0..2 {
+            scope
+                .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string())
+                .unwrap();
+        }
+
+        let type_name = std::any::type_name::<(Box<dyn Connection>, String)>();
+        assert_eq!(*chains.borrow(), vec![vec![type_name]]);
+        assert_eq!(*constructed.borrow(), vec![type_name]);
+        assert_eq!(
+            provider.stats::<(Box<dyn Connection>, String)>(),
+            ResolveStats { hits: 1, misses: 1 }
+        );
+        assert!(provider.current_chain().is_empty());
+    }
+
+    #[test]
+    fn keyed_factories_respect_the_depth_limit() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .set_max_resolution_depth(0)
+            .add_scoped_keyed_by_arg_boxed::<dyn Connection, String>(|_, tenant| {
+                Box::new(TenantConnection(tenant.clone()))
+            });
+
+        let provider = Rc::new(collection.build());
+
+        assert!(matches!(
+            provider
+                .create_scope()
+                .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string()),
+            Err(Error::ResolutionDepthExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn keyed_instances_swapped_for_another_type_are_reported() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_post_construct(|_, instance| *instance = Box::new(0u8))
+            .add_scoped_keyed_by_arg_boxed::<dyn Connection, String>(|_, tenant| {
+                Box::new(TenantConnection(tenant.clone()))
+            });
+
+        let provider = Rc::new(collection.build());
+
+        assert!(matches!(
+            provider
+                .create_scope()
+                .get_scoped_keyed_boxed::<dyn Connection, _>("acme".to_string()),
+            Err(Error::FactoryTypeMismatch { .. })
+        ));
+    }
+}
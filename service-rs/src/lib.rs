@@ -1,23 +1,185 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
-    collections::HashMap,
-    rc::Rc,
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap, HashSet},
+    rc::{Rc, Weak},
 };
 
+mod ambient;
+mod audit;
+mod container;
+mod decorate;
+mod diagnostics;
+mod dispose;
+#[cfg(feature = "async")]
+mod dispose_async;
+#[cfg(feature = "ordered-drop")]
+mod drop_order;
+mod env;
+mod erased;
+mod fallback;
+mod graph;
+mod inject;
+mod keyed;
+mod macros;
+mod memoize;
+mod metadata;
+mod multi;
+mod overrides;
+mod platform;
+mod plugin;
+mod resolve;
+#[cfg(feature = "async")]
+mod resolve_async;
+mod resolver;
+mod schema;
+mod scope_events;
+mod shared;
+mod stats;
+mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "log")]
+mod trace_log;
+mod tree;
+mod unboxed;
+mod unwind;
+
+use dispose::ServiceDisposer;
+#[cfg(feature = "async")]
+use dispose_async::AsyncServiceDisposer;
+use inject::Injection;
+use keyed::KeyedFactory;
+use multi::OrderedDescriptor;
+#[cfg(feature = "async")]
+use resolve_async::{AsyncFactory, InFlight};
+use scope_events::ScopeHook;
+use unwind::OnUnwind;
+
+pub use ambient::{CurrentProviderGuard, current, resolve_boxed};
+pub use audit::{AuditCategory, AuditWarning};
+pub use dispose::Disposable;
+#[cfg(feature = "async")]
+pub use dispose_async::{AsyncDisposable, AsyncScope};
+pub use fallback::FallbackAttempt;
+pub use graph::DependencyGraph;
+pub use inject::{Inject, InjectTarget, Injected, WeakInject};
+pub use memoize::MemoizingProvider;
+pub use overrides::Overrides;
+pub use platform::Environment;
+pub use plugin::Plugin;
+pub use resolve::Resolve;
+pub use resolver::Resolver;
+pub use schema::ServiceSchema;
+pub use shared::SharedSingletonBuilder;
+pub use stats::ResolveStats;
+pub use sync::{SyncScopedServiceProvider, SyncServiceCollection, SyncServiceProvider};
+pub use tree::ResolvedTree;
+pub use unboxed::Unboxed;
+
+// factories resolve their dependencies by recursing into `get`, so the nesting
+// is bounded to turn a runaway graph into an error instead of a stack overflow.
+pub const DEFAULT_MAX_RESOLUTION_DEPTH: usize = 128;
+
+/// How long a resolved service instance is kept around by the container.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub(crate) enum ServiceLifetime {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Lifetime {
     Singleton,
     Scoped,
     Transient,
 }
 
-type ServiceFactory = Box<dyn Fn(&ServiceProvider) -> Box<dyn Any>>;
+pub(crate) type ServiceCache = RefCell<HashMap<TypeId, Rc<dyn Any>>>;
+
+// the caches of the scope a resolution runs in
+#[derive(Debug, Clone)]
+pub(crate) struct ActiveScope {
+    pub(crate) services: Rc<ServiceCache>,
+    // caches inherited from ancestor scopes, nearest first
+    pub(crate) inherited: Rc<[Rc<ServiceCache>]>,
+    pub(crate) locals: Rc<ServiceCache>,
+    pub(crate) creation_order: Rc<RefCell<Vec<TypeId>>>,
+    pub(crate) id: u64,
+}
+
+/// Type-erased factory stored for every registration, it has to return a boxed
+/// value of exactly the type its registration is keyed by.
+///
+/// Factories are reference counted rather than boxed so that a collection can
+/// be cloned cheaply, with the clones sharing the factories. Build one with
+/// `Rc::new(|provider| ...)`.
+pub type ServiceFactory = Rc<dyn Fn(&ServiceProvider) -> Result<Box<dyn Any>, Error>>;
+
+/// Infallible factory accepted by [`ServiceCollection::add_raw`].
+pub type RawServiceFactory = Box<dyn Fn(&ServiceProvider) -> Box<dyn Any>>;
+
+// a factory may hand out an already shared instance instead of a fresh one,
+// in that case the provider caches the inner `Rc` as-is instead of re-wrapping it.
+pub(crate) struct SharedInstance(pub(crate) Rc<dyn Any>);
+
+// sits in the root cache while a singleton's factory runs, so a factory that
+// resolves its own service fails instead of recursing
+struct UnderConstruction;
+
+fn into_shared(instance: Box<dyn Any>) -> Rc<dyn Any> {
+    match instance.downcast::<SharedInstance>() {
+        Ok(shared) => shared.0,
+        Err(instance) => Rc::from(instance),
+    }
+}
+
+// debug builds include both type ids when the cached instance isn't a `T`,
+// which usually means a factory produced something other than its registered key.
+pub(crate) fn downcast_service<T: Any + 'static>(
+    service: Rc<dyn Any>,
+    type_name: &str,
+) -> Result<Rc<T>, Error> {
+    service.downcast::<T>().map_err(|service| {
+        if cfg!(debug_assertions) {
+            Error::ServiceNotFound(format!(
+                "{} (stored {:?}, requested {:?})",
+                type_name,
+                (*service).type_id(),
+                TypeId::of::<T>()
+            ))
+        } else {
+            Error::ServiceNotFound(type_name.to_string())
+        }
+    })
+}
 
-pub(crate) struct ServiceDescriptor {
-    pub(crate) lifetime: ServiceLifetime,
+#[derive(Clone)]
+pub struct ServiceDescriptor {
+    pub(crate) lifetime: Lifetime,
     pub(crate) factory: ServiceFactory,
     pub(crate) type_name: &'static str,
+    pub(crate) shared_within_resolution: bool,
+    pub(crate) cache_in_scope: bool,
+    pub(crate) enabled: bool,
+    pub(crate) metadata: HashMap<&'static str, String>,
+    pub(crate) disposer: Option<ServiceDisposer>,
+    #[cfg(feature = "async")]
+    pub(crate) async_disposer: Option<AsyncServiceDisposer>,
+}
+
+impl ServiceDescriptor {
+    /// Describes a registration built outside of the generic `add_*` methods,
+    /// e.g. by generated code. `type_name` is only used for diagnostics.
+    pub fn new(lifetime: Lifetime, factory: ServiceFactory, type_name: &'static str) -> Self {
+        ServiceDescriptor {
+            lifetime,
+            factory,
+            type_name,
+            shared_within_resolution: false,
+            cache_in_scope: false,
+            enabled: true,
+            metadata: HashMap::new(),
+            disposer: None,
+            #[cfg(feature = "async")]
+            async_disposer: None,
+        }
+    }
 }
 
 impl std::fmt::Debug for ServiceDescriptor {
@@ -25,30 +187,171 @@ impl std::fmt::Debug for ServiceDescriptor {
         f.debug_struct("ServiceDescriptor")
             .field("lifetime", &self.lifetime)
             .field("type_name", &self.type_name)
+            .field("shared_within_resolution", &self.shared_within_resolution)
+            .field("cache_in_scope", &self.cache_in_scope)
+            .field("enabled", &self.enabled)
+            .field("metadata", &self.metadata)
+            .field("disposable", &self.disposer.is_some())
             .finish()
     }
 }
 
-#[derive(Debug, Default)]
+type PostConstructFn = Rc<dyn Fn(&'static str, &mut Box<dyn Any>)>;
+
+#[derive(Clone)]
+pub(crate) struct PostConstruct(pub(crate) PostConstructFn);
+
+impl std::fmt::Debug for PostConstruct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PostConstruct")
+    }
+}
+
+/// Services are keyed by the type they are resolved as:
+///
+/// | registered with                 | key           | resolved with                             |
+/// |---------------------------------|---------------|-------------------------------------------|
+/// | `add_singleton::<C>`            | `C`           | `get::<C>()`                              |
+/// | `add_singleton_boxed::<C>`      | `Box<C>`      | `get_boxed::<C>()`, `get::<Box<C>>()`     |
+/// | `add_singleton_boxed::<dyn T>`  | `Box<dyn T>`  | `get_boxed::<dyn T>()`, `get::<Box<dyn T>>()` |
+///
+/// The same goes for the other lifetimes. Unsized types can only be registered
+/// through the `_boxed` methods, and a lookup that misses only because of the
+/// `Box` names the registration it should have used.
+#[derive(Debug, Default, Clone)]
 pub struct ServiceCollection {
     pub(crate) services: HashMap<TypeId, ServiceDescriptor>,
+    pub(crate) max_resolution_depth: Option<usize>,
+    pub(crate) enumerables: HashMap<TypeId, Vec<OrderedDescriptor>>,
+    pub(crate) post_constructs: Vec<PostConstruct>,
+    pub(crate) decorators: HashSet<(TypeId, &'static str)>,
+    pub(crate) keyed: HashMap<TypeId, KeyedFactory>,
+    pub(crate) deny_transient_in_singleton: bool,
+    pub(crate) env_singletons: Vec<(TypeId, &'static str)>,
+    pub(crate) scope_created_hooks: Vec<ScopeHook>,
+    pub(crate) scope_dropped_hooks: Vec<ScopeHook>,
+    pub(crate) environment: Environment,
+    // type names of registrations replaced by a later one
+    pub(crate) replaced: Vec<&'static str>,
+    #[cfg(feature = "async")]
+    pub(crate) async_factories: HashMap<TypeId, AsyncFactory>,
 }
 
 #[derive(Debug, Default)]
 pub struct ServiceProvider {
     pub(crate) collection: ServiceCollection,
     pub(crate) services: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    pub(crate) stack: RefCell<Vec<(TypeId, &'static str, Lifetime)>>,
+    pub(crate) trace: RefCell<Option<BTreeSet<(&'static str, &'static str)>>>,
+    pub(crate) overrides: RefCell<Option<Overrides>>,
+    pub(crate) stats: RefCell<HashMap<TypeId, ResolveStats>>,
+    pub(crate) scopes: RefCell<Vec<ActiveScope>>,
+    pub(crate) sealed: Cell<bool>,
+    pub(crate) max_cached_instances: Cell<Option<usize>>,
+    pub(crate) resolution_cache: ServiceCache,
+    pub(crate) creation_order: RefCell<Vec<RootEntry>>,
+    pub(crate) enumerable_cache: RefCell<HashMap<(TypeId, usize), Rc<dyn Any>>>,
+    pub(crate) injections: RefCell<Vec<Injection>>,
+    pub(crate) shared: Rc<HashMap<TypeId, Rc<dyn Any>>>,
+    pub(crate) this: RefCell<Weak<ServiceProvider>>,
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) disabled: RefCell<HashSet<TypeId>>,
+    pub(crate) next_scope_id: Cell<u64>,
+    pub(crate) recorded: RefCell<Option<HashMap<&'static str, Rc<dyn Any>>>>,
+    #[cfg(feature = "async")]
+    pub(crate) in_flight: RefCell<HashMap<TypeId, Rc<InFlight>>>,
 }
 
-#[derive(Debug, Default)]
+// an instance cached by the root provider, as recorded in `creation_order`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RootEntry {
+    Service(TypeId),
+    // the implementation at `index` of an ordered service or set
+    Member(TypeId, usize),
+}
+
+// everything the root provider caches, swapped out while resolving against
+// throwaway state
+#[derive(Default, Clone)]
+pub(crate) struct RootCaches {
+    services: HashMap<TypeId, Rc<dyn Any>>,
+    enumerables: HashMap<(TypeId, usize), Rc<dyn Any>>,
+    creation_order: Vec<RootEntry>,
+}
+
+/// A scope keeps its root provider alive through a strong `Rc`, and owns the
+/// scoped instances it has cached. Services that need to reach the provider
+/// after construction should hold [`ScopedServiceProvider::provider_weak`]
+/// instead of a strong handle, otherwise a service cached in the scope that
+/// points back at the container forms an `Rc` cycle and is never dropped.
+#[derive(Default)]
 pub struct ScopedServiceProvider {
     pub(crate) provider: Rc<ServiceProvider>,
-    pub(crate) services: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    pub(crate) id: u64,
+    // a handle onto the caches of another scope, which reports its own events
+    pub(crate) is_view: bool,
+    pub(crate) services: Rc<ServiceCache>,
+    pub(crate) inherited: Rc<[Rc<ServiceCache>]>,
+    pub(crate) locals: Rc<ServiceCache>,
+    pub(crate) creation_order: Rc<RefCell<Vec<TypeId>>>,
+    pub(crate) keyed: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    pub(crate) disposers: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
+
+impl std::fmt::Debug for ScopedServiceProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopedServiceProvider")
+            .field("provider", &self.provider)
+            .field("id", &self.id)
+            .field("services", &self.services)
+            .field("inherited", &self.inherited.len())
+            .field("locals", &self.locals.borrow().len())
+            .field("keyed", &self.keyed.borrow().len())
+            .field("disposers", &self.disposers.borrow().len())
+            .finish()
+    }
+}
+
+impl Drop for ScopedServiceProvider {
+    fn drop(&mut self) {
+        while let Some(dispose) = self.disposers.get_mut().pop() {
+            dispose();
+        }
+
+        #[cfg(feature = "ordered-drop")]
+        self.release_cached();
+
+        if !self.is_view {
+            self.provider.scope_dropped(self.id);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Error {
     ServiceNotFound(String),
+    ResolutionDepthExceeded(String),
+    ProviderSealed(String),
+    CacheLimitReached(String),
+    DependencyCycle(String),
+    AmbiguousService(String),
+    AlreadyResolved(String),
+    SingletonReentrancy(String),
+    ResolveTimeout(String),
+    ServiceDisabled(String),
+    NotTransient(String),
+    NotCached(String),
+    TransientCapturedBySingleton {
+        singleton: String,
+        transient: String,
+    },
+    FactoryTypeMismatch {
+        registered: &'static str,
+    },
+    OrderedResolutionFailed {
+        index: usize,
+        error: Box<Error>,
+    },
     Unknown(String),
 }
 
@@ -58,6 +361,63 @@ impl std::fmt::Display for Error {
             Error::ServiceNotFound(service_name) => {
                 write!(f, "Service not found: {}", service_name)
             }
+            Error::ResolutionDepthExceeded(service_name) => {
+                write!(f, "Resolution depth exceeded: {}", service_name)
+            }
+            Error::ProviderSealed(service_name) => {
+                write!(f, "Provider is sealed, can't cache: {}", service_name)
+            }
+            Error::CacheLimitReached(service_name) => {
+                write!(f, "Cached instance limit reached: {}", service_name)
+            }
+            Error::DependencyCycle(path) => write!(f, "Dependency cycle: {}", path),
+            Error::AmbiguousService(name) => {
+                write!(f, "Service name matches several services: {}", name)
+            }
+            Error::AlreadyResolved(service_name) => {
+                write!(
+                    f,
+                    "Service already resolved in this scope: {}",
+                    service_name
+                )
+            }
+            Error::SingletonReentrancy(service_name) => {
+                write!(
+                    f,
+                    "Singleton resolved while being constructed: {}",
+                    service_name
+                )
+            }
+            Error::ResolveTimeout(service_name) => {
+                write!(f, "Timed out resolving: {}", service_name)
+            }
+            Error::ServiceDisabled(service_name) => {
+                write!(f, "Service is disabled: {}", service_name)
+            }
+            Error::NotTransient(service_name) => {
+                write!(f, "Service is shared, can't be owned: {}", service_name)
+            }
+            Error::NotCached(service_name) => {
+                write!(
+                    f,
+                    "Service isn't cached, can't be referenced weakly: {}",
+                    service_name
+                )
+            }
+            Error::TransientCapturedBySingleton {
+                singleton,
+                transient,
+            } => write!(
+                f,
+                "Transient {} resolved by singleton {}",
+                transient, singleton
+            ),
+            Error::FactoryTypeMismatch { registered } => {
+                write!(f, "Factory returned the wrong type for: {}", registered)
+            }
+            Error::OrderedResolutionFailed { index, error } => {
+                write!(f, "Resolution at index {} failed: {}", index, error)
+            }
             Error::Unknown(message) => write!(f, "Unknown error: {}", message),
         }
     }
@@ -68,262 +428,2188 @@ impl ServiceCollection {
         Self::default()
     }
 
-    pub fn add_singleton_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    /// Builds a collection from precomputed registrations, a later entry for
+    /// the same `TypeId` replaces an earlier one like the `add_*` methods do.
+    pub fn from_descriptors(entries: Vec<(TypeId, ServiceDescriptor)>) -> Self {
+        ServiceCollection {
+            services: entries.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Number of registered services, multi-registrations not included.
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Copies every registration, for building variants of a shared base set
+    /// (prod and staging, ...). The copies share their factories, hooks and
+    /// disposers, so state captured by a factory is shared too. This can't
+    /// fail at the moment, the `Result` leaves room for registrations that
+    /// can't be copied.
+    pub fn try_clone(&self) -> Result<ServiceCollection, Error> {
+        Ok(self.clone())
+    }
+
+    pub(crate) fn register(
+        &mut self,
+        key: TypeId,
+        type_name: &'static str,
+        lifetime: Lifetime,
+        factory: ServiceFactory,
+    ) -> &mut Self {
+        let descriptor = ServiceDescriptor::new(lifetime, factory, type_name);
+        if self.services.insert(key, descriptor).is_some() {
+            self.replaced.push(type_name);
+        }
+
+        self
+    }
+
+    fn register_boxed<T: ?Sized + 'static, F>(
+        &mut self,
+        lifetime: Lifetime,
+        factory: F,
+    ) -> &mut Self
     where
-        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+        F: Fn(&ServiceProvider) -> Result<Box<T>, Error> + 'static,
     {
-        let key = TypeId::of::<Box<T>>();
-        let type_name = std::any::type_name::<Box<T>>();
+        self.register(
+            TypeId::of::<Box<T>>(),
+            std::any::type_name::<Box<T>>(),
+            lifetime,
+            Rc::new(move |provider| Ok(Box::new(factory(provider)?) as Box<dyn Any>)),
+        )
+    }
+
+    fn register_any<T: Any + 'static, F>(&mut self, lifetime: Lifetime, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<dyn Any> + 'static,
+    {
+        self.add_raw(
+            TypeId::of::<T>(),
+            lifetime,
+            std::any::type_name::<T>(),
+            Box::new(factory),
+        )
+    }
 
-        self.services.insert(
+    /// Registers a factory under `key` without knowing the service type at
+    /// compile time, e.g. for plugins handing over their factories at runtime.
+    ///
+    /// The factory has to return a value of exactly the type `key` identifies,
+    /// otherwise resolving it fails with [`Error::FactoryTypeMismatch`].
+    pub fn add_raw(
+        &mut self,
+        key: TypeId,
+        lifetime: Lifetime,
+        type_name: &'static str,
+        factory: RawServiceFactory,
+    ) -> &mut Self {
+        self.register(
             key,
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Singleton,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name,
-            },
-        );
+            type_name,
+            lifetime,
+            Rc::new(move |provider| Ok(factory(provider))),
+        )
+    }
 
-        self
+    /// Registers a singleton built on first resolution.
+    ///
+    /// The factory may resolve other singletons, e.g. a `Config` to decide
+    /// which implementation to return. Registration order doesn't matter: a
+    /// dependency is built on demand, inside the factory's call to `get`, and is
+    /// cached before that call returns. So dependencies always finish
+    /// construction before their dependents, and every dependent sees the same
+    /// dependency instance.
+    pub fn add_singleton_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Singleton, move |provider| Ok(factory(provider)))
+    }
+
+    /// Same as `add_singleton_boxed` with a plain function instead of a
+    /// closure, for factories that capture nothing.
+    ///
+    /// The function is still called through the registration's erased factory:
+    /// a singleton's factory runs once, after which `get` is served from the
+    /// cache, so a separate function pointer table wouldn't save anything.
+    pub fn add_singleton_fn_boxed<T: ?Sized + 'static>(
+        &mut self,
+        f: fn(&ServiceProvider) -> Box<T>,
+    ) -> &mut Self {
+        self.add_singleton_boxed::<T, _>(f)
     }
 
     pub fn add_transient_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
     where
         F: Fn(&ServiceProvider) -> Box<T> + 'static,
     {
-        let key = TypeId::of::<Box<T>>();
-        let type_name = std::any::type_name::<Box<T>>();
+        self.register_boxed::<T, _>(Lifetime::Transient, move |provider| Ok(factory(provider)))
+    }
 
-        self.services.insert(
-            key,
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Transient,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name,
-            },
-        );
+    pub fn add_scoped_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Scoped, move |provider| Ok(factory(provider)))
+    }
+
+    /// Registers a transient that is cached by the scope resolving it, so it
+    /// behaves like a scoped service inside scopes (one instance per scope)
+    /// while the root provider still builds a fresh instance on every `get`.
+    pub fn add_transient_cached_in_scope_boxed<T: ?Sized + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        self.add_transient_boxed::<T, _>(factory);
+
+        if let Some(descriptor) = self.services.get_mut(&TypeId::of::<Box<T>>()) {
+            descriptor.cache_in_scope = true;
+        }
 
         self
     }
 
-    pub fn add_scoped_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    /// Makes the singleton `Box<T>` honor the scope resolving it: a scope
+    /// caches its own instance, like a scoped service, while resolutions from
+    /// the root provider share the root's singleton. Meant for caches that are
+    /// shared per request when there is one and globally otherwise.
+    ///
+    /// Does nothing if `T` isn't registered yet.
+    pub fn prefer_scope_boxed<T: ?Sized + 'static>(&mut self) -> &mut Self {
+        if let Some(descriptor) = self.services.get_mut(&TypeId::of::<Box<T>>()) {
+            descriptor.cache_in_scope = true;
+        }
+
+        self
+    }
+
+    /// Registers a transient that is built at most once per top-level
+    /// resolution: every consumer within one `get` shares the instance, while
+    /// separate `get` calls still receive fresh ones.
+    pub fn add_transient_shared_within_resolution_boxed<T: ?Sized + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
     where
         F: Fn(&ServiceProvider) -> Box<T> + 'static,
     {
-        let key = TypeId::of::<Box<T>>();
-        let type_name = std::any::type_name::<Box<T>>();
+        self.add_transient_boxed::<T, _>(factory);
 
-        self.services.insert(
-            key,
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Scoped,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name,
-            },
-        );
+        if let Some(descriptor) = self.services.get_mut(&TypeId::of::<Box<T>>()) {
+            descriptor.shared_within_resolution = true;
+        }
 
         self
     }
 
-    pub fn add_singleton<T: Any + 'static, F>(&mut self, factory: F) -> &mut Self
+    pub fn try_add_singleton_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
     where
-        F: Fn(&ServiceProvider) -> Box<dyn Any> + 'static,
+        F: Fn(&ServiceProvider) -> Result<Box<T>, Error> + 'static,
     {
-        self.services.insert(
-            TypeId::of::<T>(),
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Singleton,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name: std::any::type_name::<T>(),
-            },
-        );
+        self.register_boxed::<T, _>(Lifetime::Singleton, factory)
+    }
 
-        self
+    pub fn try_add_transient_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Result<Box<T>, Error> + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Transient, factory)
+    }
+
+    pub fn try_add_scoped_boxed<T: ?Sized + 'static, F>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Result<Box<T>, Error> + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Scoped, factory)
     }
 
-    pub fn add_transient<T: Any + 'static, F>(&mut self, factory: F) -> &mut Self
+    /// Registers `T` as a singleton. To also resolve the same instance as a
+    /// `dyn Trait`, register it with [`ServiceCollection::add_singleton_shared`]
+    /// and add the trait views on the returned builder.
+    #[allow(clippy::needless_maybe_sized)]
+    pub fn add_singleton<T: ?Sized + Unboxed, F>(&mut self, factory: F) -> &mut Self
     where
         F: Fn(&ServiceProvider) -> Box<dyn Any> + 'static,
     {
-        self.services.insert(
-            TypeId::of::<T>(),
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Transient,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name: std::any::type_name::<T>(),
-            },
-        );
+        self.register_any::<T, _>(Lifetime::Singleton, factory)
+    }
 
-        self
+    #[allow(clippy::needless_maybe_sized)]
+    pub fn add_transient<T: ?Sized + Unboxed, F>(&mut self, factory: F) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<dyn Any> + 'static,
+    {
+        self.register_any::<T, _>(Lifetime::Transient, factory)
     }
 
-    pub fn add_scoped<T: Any + 'static, F>(&mut self, factory: F) -> &mut Self
+    #[allow(clippy::needless_maybe_sized)]
+    pub fn add_scoped<T: ?Sized + Unboxed, F>(&mut self, factory: F) -> &mut Self
     where
         F: Fn(&ServiceProvider) -> Box<dyn Any> + 'static,
     {
-        self.services.insert(
-            TypeId::of::<T>(),
-            ServiceDescriptor {
-                lifetime: ServiceLifetime::Scoped,
-                factory: Box::new(move |provider| Box::new(factory(provider)) as Box<dyn Any>),
-                type_name: std::any::type_name::<T>(),
-            },
-        );
+        self.register_any::<T, _>(Lifetime::Scoped, factory)
+    }
+
+    // explains a miss caused by resolving a boxed registration unboxed, or the
+    // other way around
+    pub(crate) fn not_found_hint(&self, type_name: &str) -> String {
+        let box_prefix = std::any::type_name::<Box<()>>().trim_end_matches("()>");
+        let registered = |name: &str| {
+            self.services
+                .values()
+                .any(|descriptor| descriptor.type_name == name)
+        };
+
+        let boxed = format!("{}{}>", box_prefix, type_name);
+        if registered(&boxed) {
+            return format!(
+                "{} (registered as {}, resolve it with get_boxed)",
+                type_name, boxed
+            );
+        }
+
+        if let Some(unboxed) = type_name
+            .strip_prefix(box_prefix)
+            .and_then(|name| name.strip_suffix('>'))
+            && registered(unboxed)
+        {
+            return format!(
+                "{} (registered as {}, resolve it with get)",
+                type_name, unboxed
+            );
+        }
+
+        type_name.to_string()
+    }
+
+    /// The factory registered for `T` (`Box<dyn Trait>` for boxed
+    /// registrations), for tooling that invokes or wraps it directly.
+    ///
+    /// The factory returns the instance erased as `Box<dyn Any>`, holding a
+    /// `T` for most registrations. Mocked registrations and some helpers return
+    /// an internal wrapper instead, so downcast the output to `T` and don't
+    /// assume it succeeds. Calling the factory bypasses the caches, the depth
+    /// limit and post-construct hooks.
+    pub fn factory_ref<T: Any>(&self) -> Option<&ServiceFactory> {
+        self.services
+            .get(&TypeId::of::<T>())
+            .map(|descriptor| &descriptor.factory)
+    }
+
+    pub fn mock_boxed<T: ?Sized + 'static>(&mut self, mock: Box<T>) -> &mut Self {
+        let instance: Rc<dyn Any> = Rc::new(mock);
+        // replacing the registration is the point, it's no duplicate
+        self.services.remove(&TypeId::of::<Box<T>>());
+
+        self.register(
+            TypeId::of::<Box<T>>(),
+            std::any::type_name::<Box<T>>(),
+            Lifetime::Singleton,
+            Rc::new(move |_| Ok(Box::new(SharedInstance(instance.clone())) as Box<dyn Any>)),
+        )
+    }
+
+    /// Adds a hook that runs on the output of every factory, before the
+    /// instance is cached or returned. Hooks run in the order they were added
+    /// and receive the registered type name alongside the erased instance.
+    pub fn add_post_construct(
+        &mut self,
+        post_construct: impl Fn(&'static str, &mut Box<dyn Any>) + 'static,
+    ) -> &mut Self {
+        self.post_constructs
+            .push(PostConstruct(Rc::new(post_construct)));
+        self
+    }
+
+    /// Makes resolving a transient while a singleton factory runs fail with
+    /// [`Error::TransientCapturedBySingleton`], since the singleton would hold
+    /// on to that one transient instance forever.
+    pub fn deny_transient_in_singleton(&mut self) -> &mut Self {
+        self.deny_transient_in_singleton = true;
+        self
+    }
+
+    /// Disables or re-enables the registration of `T` without removing it, for
+    /// feature-flag rollouts. Resolving a disabled service fails with
+    /// [`Error::ServiceDisabled`].
+    ///
+    /// Does nothing if `T` isn't registered.
+    pub fn set_enabled<T: Any>(&mut self, enabled: bool) -> &mut Self {
+        if let Some(descriptor) = self.services.get_mut(&TypeId::of::<T>()) {
+            descriptor.enabled = enabled;
+        }
+
+        self
+    }
 
+    pub fn set_max_resolution_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_resolution_depth = Some(depth);
         self
     }
 
+    /// Builds the provider. If singletons were registered with
+    /// `add_singleton_from_env`, the process environment is read once here and
+    /// they are built right away.
     pub fn build(self) -> ServiceProvider {
-        ServiceProvider {
+        let env = if self.env_singletons.is_empty() {
+            HashMap::new()
+        } else {
+            env::snapshot()
+        };
+
+        self.build_with_env(env)
+    }
+
+    /// Same as `build`, with `env` standing in for the process environment of
+    /// the `add_singleton_from_env` registrations.
+    pub fn build_with_env(self, env: HashMap<String, String>) -> ServiceProvider {
+        let provider = ServiceProvider {
             collection: self,
             services: RefCell::new(HashMap::new()),
-        }
+            stack: RefCell::new(Vec::new()),
+            trace: RefCell::new(None),
+            overrides: RefCell::new(None),
+            stats: RefCell::new(HashMap::new()),
+            scopes: RefCell::new(Vec::new()),
+            sealed: Cell::new(false),
+            max_cached_instances: Cell::new(None),
+            resolution_cache: RefCell::new(HashMap::new()),
+            creation_order: RefCell::new(Vec::new()),
+            enumerable_cache: RefCell::new(HashMap::new()),
+            injections: RefCell::new(Vec::new()),
+            shared: Rc::new(HashMap::new()),
+            this: RefCell::new(Weak::new()),
+            env,
+            disabled: RefCell::new(HashSet::new()),
+            next_scope_id: Cell::new(0),
+            recorded: RefCell::new(None),
+            #[cfg(feature = "async")]
+            in_flight: RefCell::new(HashMap::new()),
+        };
+
+        provider.build_env_singletons();
+        provider
+    }
+
+    /// Builds a provider that serves the singletons in `shared` as-is before
+    /// building its own, so several providers (e.g. the old and new one during
+    /// a config reload) can share heavy immutable instances.
+    pub fn build_with_shared(self, shared: Rc<HashMap<TypeId, Rc<dyn Any>>>) -> ServiceProvider {
+        let mut provider = self.build();
+        provider.shared = shared;
+        provider
+    }
+}
+
+impl From<Vec<(TypeId, ServiceDescriptor)>> for ServiceCollection {
+    fn from(entries: Vec<(TypeId, ServiceDescriptor)>) -> Self {
+        Self::from_descriptors(entries)
     }
 }
 
 impl ServiceProvider {
+    /// Registers the services with `f` on a new collection and builds it:
+    /// `ServiceProvider::configure(|services| { services.add_singleton_boxed(...); })`.
+    pub fn configure(f: impl FnOnce(&mut ServiceCollection)) -> Rc<ServiceProvider> {
+        let mut collection = ServiceCollection::new();
+        f(&mut collection);
+        collection.build_rc()
+    }
+
     pub fn create_scope(self: &Rc<Self>) -> ScopedServiceProvider {
-        ScopedServiceProvider {
-            provider: self.clone(),
-            services: RefCell::new(HashMap::new()),
+        ScopedServiceProvider::from_provider(self.clone())
+    }
+
+    /// Stops the root cache from growing: resolving a singleton that isn't
+    /// cached yet fails with
+    /// [`Error::ProviderSealed`]. Call it once every singleton is warmed up.
+    ///
+    /// Transients and the caches of individual scopes are not affected.
+    pub fn seal(&self) {
+        self.sealed.set(true);
+    }
+
+    /// Caps how many instances the root cache may hold. Once `n` instances are
+    /// cached, resolving another uncached singleton fails with
+    /// [`Error::CacheLimitReached`]. Transients don't count.
+    pub fn set_max_cached_instances(&self, n: usize) {
+        self.max_cached_instances.set(Some(n));
+    }
+
+    /// Number of instances currently held by the root cache.
+    pub fn cached_instances(&self) -> usize {
+        self.services.borrow().len() + self.enumerable_cache.borrow().len()
+    }
+
+    /// Disables or re-enables `T` on the built provider, e.g. to switch off a
+    /// misbehaving service in a live system. While disabled, resolving `T`
+    /// fails with [`Error::ServiceDisabled`] without looking at any cache, but
+    /// instances already cached are kept for when it's enabled again; drop
+    /// them with [`ServiceProvider::evict`].
+    ///
+    /// A registration disabled on the collection stays disabled.
+    pub fn set_enabled<T: Any>(&self, enabled: bool) {
+        let mut disabled = self.disabled.borrow_mut();
+
+        if enabled {
+            disabled.remove(&TypeId::of::<T>());
+        } else {
+            disabled.insert(TypeId::of::<T>());
         }
     }
 
-    pub fn get_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Rc<Box<T>>, Error> {
-        self.get::<Box<T>>()
+    pub(crate) fn is_disabled(&self, type_id: &TypeId) -> bool {
+        let disabled = self.disabled.borrow();
+        !disabled.is_empty() && disabled.contains(type_id)
     }
 
-    pub fn get<T: Any + 'static>(&self) -> Result<Rc<T>, Error> {
+    /// Drops the instance of `T` cached by the root provider, if any, without
+    /// disposing it. Returns whether there was one. Instances cached by scopes
+    /// are left alone.
+    pub fn evict<T: Any>(&self) -> bool {
         let type_id = TypeId::of::<T>();
-        let type_name = std::any::type_name::<T>();
+        let evicted = self.services.borrow_mut().remove(&type_id).is_some();
 
-        let lifetime = self
-            .collection
-            .services
-            .get(&type_id)
-            .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-            .lifetime
-            .clone();
-
-        match lifetime {
-            ServiceLifetime::Singleton => {
-                if let Some(service) = self.services.borrow().get(&type_id) {
-                    return service
-                        .clone()
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()));
-                } else {
-                    let instance = self
-                        .collection
-                        .services
-                        .get(&type_id)
-                        .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-                        .factory
-                        .as_ref()(self);
-
-                    let rc_any = Rc::<dyn Any>::from(instance);
-
-                    self.services
-                        .borrow_mut()
-                        .insert(type_id, Rc::from(rc_any.clone()));
+        if evicted {
+            self.creation_order
+                .borrow_mut()
+                .retain(|created| *created != RootEntry::Service(type_id));
+        }
 
-                    rc_any
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()))
-                }
-            }
-            ServiceLifetime::Transient => {
-                let instance = self
-                    .collection
-                    .services
-                    .get(&type_id)
-                    .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-                    .factory
-                    .as_ref()(self);
+        evicted
+    }
 
-                let rc_any = Rc::<dyn Any>::from(instance);
+    pub(crate) fn ensure_cacheable(&self, type_name: &'static str) -> Result<(), Error> {
+        if self.sealed.get() {
+            return Err(Error::ProviderSealed(type_name.to_string()));
+        }
 
-                rc_any
-                    .downcast::<T>()
-                    .map_err(|_| Error::ServiceNotFound(type_name.to_string()))
-            }
-            ServiceLifetime::Scoped => {
-                if let Some(service) = self.services.borrow().get(&type_id) {
-                    return service
-                        .clone()
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()));
-                } else {
-                    let instance = self
-                        .collection
-                        .services
-                        .get(&type_id)
-                        .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-                        .factory
-                        .as_ref()(self);
-
-                    let rc_any = Rc::<dyn Any>::from(instance);
-
-                    self.services
-                        .borrow_mut()
-                        .insert(type_id, Rc::from(rc_any.clone()));
+        if let Some(limit) = self.max_cached_instances.get()
+            && self.cached_instances() >= limit
+        {
+            return Err(Error::CacheLimitReached(type_name.to_string()));
+        }
 
-                    rc_any
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()))
-                }
-            }
+        Ok(())
+    }
+
+    pub(crate) fn descriptor(
+        &self,
+        type_id: &TypeId,
+        type_name: &str,
+    ) -> Result<&ServiceDescriptor, Error> {
+        match self.collection.services.get(type_id) {
+            Some(descriptor) if descriptor.enabled && !self.is_disabled(type_id) => Ok(descriptor),
+            Some(_) => Err(Error::ServiceDisabled(type_name.to_string())),
+            None => Err(Error::ServiceNotFound(
+                self.collection.not_found_hint(type_name),
+            )),
         }
     }
-}
 
-impl ScopedServiceProvider {
-    pub fn get_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Rc<Box<T>>, Error> {
-        self.get::<Box<T>>()
+    // runs the registered factory for `type_id`, every nested `get` made by the
+    // factory counts one level towards the maximum resolution depth.
+    pub(crate) fn create(
+        &self,
+        type_id: &TypeId,
+        type_name: &'static str,
+    ) -> Result<Rc<dyn Any>, Error> {
+        self.create_from(self.descriptor(type_id, type_name)?, type_id)
     }
 
-    pub fn get<T: Any + 'static>(&self) -> Result<Rc<T>, Error> {
-        let type_id = TypeId::of::<T>();
-        let type_name = std::any::type_name::<T>();
+    pub(crate) fn create_from(
+        &self,
+        descriptor: &ServiceDescriptor,
+        type_id: &TypeId,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let service = self.instantiate(descriptor, type_id)?;
+
+        // erased factories (`add_singleton`, `add_raw`, ...) can return anything
+        if (*service).type_id() != *type_id {
+            return Err(Error::FactoryTypeMismatch {
+                registered: descriptor.type_name,
+            });
+        }
+
+        Ok(service)
+    }
+
+    pub(crate) fn root_caches(&self) -> RootCaches {
+        RootCaches {
+            services: self.services.borrow().clone(),
+            enumerables: self.enumerable_cache.borrow().clone(),
+            creation_order: self.creation_order.borrow().clone(),
+        }
+    }
+
+    // returns the caches replaced by `caches`
+    pub(crate) fn replace_root_caches(&self, caches: RootCaches) -> RootCaches {
+        RootCaches {
+            services: self.services.replace(caches.services),
+            enumerables: self.enumerable_cache.replace(caches.enumerables),
+            creation_order: self.creation_order.replace(caches.creation_order),
+        }
+    }
+
+    // fails if any factory up the stack belongs to a singleton
+    pub(crate) fn ensure_not_captured(&self, type_name: &'static str) -> Result<(), Error> {
+        let stack = self.stack.borrow();
+        let singleton = stack
+            .iter()
+            .rev()
+            .find(|(_, _, lifetime)| *lifetime == Lifetime::Singleton);
+
+        match singleton {
+            Some((_, singleton, _)) => Err(Error::TransientCapturedBySingleton {
+                singleton: singleton.to_string(),
+                transient: type_name.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// How many factory calls are active above the one calling this, so `0`
+    /// inside the factory of a top-level `get` (and outside any factory).
+    pub fn current_depth(&self) -> u32 {
+        self.stack.borrow().len().saturating_sub(1) as u32
+    }
+
+    /// The types whose factories are running, outermost first, so the last
+    /// entry is the one being built by the caller. Factories can add it to
+    /// their log lines to tell why they were built.
+    pub fn current_chain(&self) -> Vec<&'static str> {
+        self.stack
+            .borrow()
+            .iter()
+            .map(|(_, type_name, _)| *type_name)
+            .collect()
+    }
+
+    pub(crate) fn instantiate(
+        &self,
+        descriptor: &ServiceDescriptor,
+        type_id: &TypeId,
+    ) -> Result<Rc<dyn Any>, Error> {
+        Ok(into_shared(self.build_instance(descriptor, type_id)?))
+    }
 
-        let lifetime = self
-            .provider
+    // runs the factory and the post-construct hooks, without wrapping the
+    // instance for the caches
+    fn build_instance(
+        &self,
+        descriptor: &ServiceDescriptor,
+        type_id: &TypeId,
+    ) -> Result<Box<dyn Any>, Error> {
+        let type_name = descriptor.type_name;
+
+        self.enter_factory(*type_id, type_name, descriptor.lifetime.clone())?;
+        let guard = OnUnwind(|| self.abandon_factory());
+        let instance = descriptor.factory.as_ref()(self);
+        guard.disarm();
+        self.finish_factory();
+
+        let mut instance = instance?;
+        self.run_post_constructs(type_name, &mut instance);
+
+        Ok(instance)
+    }
+
+    // pushes a factory about to run onto the stack, unless that goes past the
+    // depth limit
+    pub(crate) fn enter_factory(
+        &self,
+        type_id: TypeId,
+        type_name: &'static str,
+        lifetime: Lifetime,
+    ) -> Result<(), Error> {
+        let depth = self.stack.borrow().len();
+        let max_depth = self
             .collection
-            .services
-            .get(&type_id)
-            .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-            .lifetime
-            .clone();
-
-        match lifetime {
-            ServiceLifetime::Scoped => {
-                if let Some(service) = self.services.borrow().get(&type_id) {
-                    return service
-                        .clone()
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()));
-                } else {
-                    let instance = self
-                        .provider
-                        .collection
-                        .services
-                        .get(&type_id)
-                        .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?
-                        .factory
-                        .as_ref()(&self.provider);
-
-                    let rc_any = Rc::<dyn Any>::from(instance);
-
-                    self.services
-                        .borrow_mut()
-                        .insert(type_id, Rc::from(rc_any.clone()));
+            .max_resolution_depth
+            .unwrap_or(DEFAULT_MAX_RESOLUTION_DEPTH);
 
-                    rc_any
-                        .downcast::<T>()
-                        .map_err(|_| Error::ServiceNotFound(type_name.to_string()))
-                }
-            }
-            _ => self.provider.get::<T>(),
+        if depth >= max_depth {
+            return Err(Error::ResolutionDepthExceeded(type_name.to_string()));
         }
+
+        self.stack.borrow_mut().push((type_id, type_name, lifetime));
+        Ok(())
+    }
+
+    // pops the factory off the stack, the resolution ends with the outermost one
+    pub(crate) fn finish_factory(&self) {
+        self.stack.borrow_mut().pop();
+
+        if self.stack.borrow().is_empty() {
+            self.resolution_cache.borrow_mut().clear();
+        }
+    }
+
+    // unwinds out of a panicking factory
+    pub(crate) fn abandon_factory(&self) {
+        self.finish_factory();
+        // injections queued by the abandoned resolution must not run later
+        if self.stack.borrow().is_empty() {
+            self.injections.borrow_mut().clear();
+        }
+    }
+
+    pub(crate) fn run_post_constructs(&self, type_name: &'static str, instance: &mut Box<dyn Any>) {
+        // shared instances were constructed (and processed) elsewhere
+        if instance.is::<SharedInstance>() {
+            return;
+        }
+
+        for post_construct in &self.collection.post_constructs {
+            post_construct.0(type_name, instance);
+        }
+    }
+
+    // resolves a scoped service into the cache of `scope`, which stays the active
+    // scope for every nested `get` made while its factory runs. if construction
+    // fails, everything cached in the scope during the attempt is rolled back.
+    pub(crate) fn resolve_scoped(
+        &self,
+        active: &ActiveScope,
+        type_id: TypeId,
+        type_name: &'static str,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let scope = &active.services;
+        let cached = std::iter::once(scope)
+            .chain(active.inherited.iter())
+            .find_map(|cache| cache.borrow().get(&type_id).cloned());
+
+        if let Some(service) = cached {
+            self.record_hit(type_id);
+            return Ok(service);
+        }
+
+        self.record_miss(type_id);
+
+        let snapshot = scope.borrow().clone();
+
+        self.scopes.borrow_mut().push(active.clone());
+        let guard = OnUnwind(|| {
+            self.scopes.borrow_mut().pop();
+            *scope.borrow_mut() = snapshot.clone();
+        });
+        let service = self.create(&type_id, type_name);
+        guard.disarm();
+        self.scopes.borrow_mut().pop();
+
+        match service {
+            Ok(service) => {
+                scope.borrow_mut().insert(type_id, service.clone());
+                active.creation_order.borrow_mut().push(type_id);
+                Ok(service)
+            }
+            Err(error) => {
+                *scope.borrow_mut() = snapshot;
+                Err(error)
+            }
+        }
+    }
+
+    pub fn get_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Rc<Box<T>>, Error> {
+        self.get::<Box<T>>()
+    }
+
+    // the fallback instance is handed out as-is and never cached. it only
+    // stands in for an unregistered `T`, any other failure is returned
+    pub fn get_or_default_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        fallback: impl FnOnce() -> Box<T>,
+    ) -> Result<Rc<Box<T>>, Error> {
+        match self.get_boxed::<T>() {
+            Err(Error::ServiceNotFound(_))
+                if !self
+                    .collection
+                    .services
+                    .contains_key(&TypeId::of::<Box<T>>()) =>
+            {
+                Ok(Rc::new(fallback()))
+            }
+            service => service,
+        }
+    }
+
+    /// Resolves `Box<T>` only when `cond` holds, for dependencies behind a
+    /// feature flag. `Ok(None)` if it doesn't, nothing is resolved then and a
+    /// missing registration isn't an error.
+    pub fn get_if_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        cond: bool,
+    ) -> Result<Option<Rc<Box<T>>>, Error> {
+        cond.then(|| self.get_boxed::<T>()).transpose()
+    }
+
+    /// Resolves `T` if it is registered or was registered through this method
+    /// before, otherwise builds it with `factory` and caches it as a singleton.
+    ///
+    /// This only adds the instance to the provider's cache, the collection is
+    /// left as is: `get_boxed::<T>()` finds it afterwards, but it has no
+    /// descriptor, so it doesn't show up in graphs or schemas and can't be
+    /// disposed through `mark_disposable`.
+    ///
+    /// `factory` only stands in for a missing registration: a registered `T`
+    /// keeps its lifetime, and errors resolving it are returned as they are.
+    /// Caching the new instance fails like any singleton on a sealed provider
+    /// or one at its cache limit.
+    pub fn get_or_register_singleton_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        factory: impl Fn(&ServiceProvider) -> Box<T>,
+    ) -> Result<Rc<Box<T>>, Error> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+
+        match self.get_boxed::<T>() {
+            Err(Error::ServiceNotFound(_)) if !self.collection.services.contains_key(&type_id) => {}
+            service => return service,
+        }
+
+        self.ensure_cacheable(type_name)?;
+        let service: Rc<dyn Any> = Rc::new(factory(self));
+
+        self.services.borrow_mut().insert(type_id, service.clone());
+        self.creation_order
+            .borrow_mut()
+            .push(RootEntry::Service(type_id));
+
+        Ok(service
+            .downcast::<Box<T>>()
+            .unwrap_or_else(|_| unreachable!("built as Box<T>")))
+    }
+
+    #[allow(clippy::needless_maybe_sized)]
+    pub fn get<T: ?Sized + Unboxed>(&self) -> Result<Rc<T>, Error> {
+        let type_name = std::any::type_name::<T>();
+
+        downcast_service(self.resolve_any(TypeId::of::<T>(), type_name)?, type_name)
+    }
+
+    /// Resolves `Box<T>` and returns what `f` derives from it, without handing
+    /// out the `Rc`.
+    pub fn get_map_boxed<T: ?Sized + Any + 'static, R>(
+        &self,
+        f: impl FnOnce(&Box<T>) -> R,
+    ) -> Result<R, Error> {
+        self.get_map::<Box<T>, R>(f)
+    }
+
+    /// Resolves `T` and returns what `f` derives from it, e.g. a single field
+    /// of a config: `provider.get_map::<AppConfig, _>(|c| c.port)?`.
+    pub fn get_map<T: Any + 'static, R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, Error> {
+        Ok(f(&*self.get::<T>()?))
+    }
+
+    /// Same as `get`, with the error converted into the caller's error type.
+    pub fn get_into<T: Any + 'static, E: From<Error>>(&self) -> Result<Rc<T>, E> {
+        self.get::<T>().map_err(E::from)
+    }
+
+    /// Builds a transient `Box<T>` and hands it out owned, without the `Rc`
+    /// that `get_boxed` wraps it in.
+    ///
+    /// Fails with [`Error::NotTransient`] for services that are shared: singletons,
+    /// scoped services, transients shared within a resolution or cached in a
+    /// scope, and services currently overridden or seeded as scope locals.
+    pub fn get_owned_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Box<T>, Error> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+        let not_transient = || Error::NotTransient(type_name.to_string());
+
+        self.record_dependency(type_name);
+
+        let descriptor = self.descriptor(&type_id, type_name)?;
+        let shadowed = self.get_override(&type_id).is_some()
+            || self
+                .scopes
+                .borrow()
+                .last()
+                .is_some_and(|scope| scope.locals.borrow().contains_key(&type_id));
+
+        if descriptor.lifetime != Lifetime::Transient
+            || descriptor.shared_within_resolution
+            || descriptor.cache_in_scope
+            || shadowed
+        {
+            return Err(not_transient());
+        }
+
+        self.record_miss(type_id);
+        let instance = self.build_instance(descriptor, &type_id);
+        let injected = self.run_injections();
+
+        // a factory handing out a shared instance can't give up ownership
+        let instance = instance?;
+        injected?;
+        instance
+            .downcast::<Box<T>>()
+            .map(|instance| *instance)
+            .map_err(|_| not_transient())
+    }
+
+    pub(crate) fn resolve_any(
+        &self,
+        type_id: TypeId,
+        type_name: &'static str,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let service = self.resolve_uninjected(type_id, type_name);
+        self.finish_resolution(type_name, service)
+    }
+
+    // kept out of `resolve_any`, whose frame sits on every level of a nested
+    // resolution
+    fn finish_resolution(
+        &self,
+        type_name: &'static str,
+        service: Result<Rc<dyn Any>, Error>,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let injected = self.run_injections();
+        let service = service.and_then(|service| injected.map(|()| service));
+
+        if let Ok(service) = &service {
+            self.record_resolved(type_name, service);
+        }
+
+        #[cfg(feature = "log")]
+        if let Err(error) = &service {
+            trace_log::log_failure(type_name, error);
+        }
+
+        service
+    }
+
+    fn resolve_uninjected(
+        &self,
+        type_id: TypeId,
+        type_name: &'static str,
+    ) -> Result<Rc<dyn Any>, Error> {
+        self.record_dependency(type_name);
+
+        if let Some(service) = self.get_override(&type_id) {
+            return Ok(service);
+        }
+
+        let active_scope = self.scopes.borrow().last().cloned();
+
+        if let Some(local) = active_scope
+            .as_ref()
+            .and_then(|scope| scope.locals.borrow().get(&type_id).cloned())
+        {
+            return Ok(local);
+        }
+
+        let descriptor = match self.descriptor(&type_id, type_name) {
+            Ok(descriptor) => descriptor,
+            // singletons registered at runtime only live in the root cache
+            Err(error @ Error::ServiceNotFound(_)) => {
+                let cached = self.services.borrow().get(&type_id).cloned();
+                return cached
+                    .or_else(|| self.resolve_container(type_id))
+                    .ok_or(error);
+            }
+            Err(error) => return Err(error),
+        };
+        let lifetime = descriptor.lifetime.clone();
+        let shared_within_resolution = descriptor.shared_within_resolution;
+        let cache_in_scope = descriptor.cache_in_scope;
+
+        if lifetime == Lifetime::Transient && self.collection.deny_transient_in_singleton {
+            self.ensure_not_captured(type_name)?;
+        }
+
+        match (lifetime, active_scope) {
+            (Lifetime::Scoped, Some(scope)) => self.resolve_scoped(&scope, type_id, type_name),
+            (Lifetime::Transient | Lifetime::Singleton, Some(scope)) if cache_in_scope => {
+                self.resolve_scoped(&scope, type_id, type_name)
+            }
+            (Lifetime::Singleton, _) => self.resolve_root(type_id, type_name),
+            (Lifetime::Transient, _) if shared_within_resolution => {
+                self.resolve_within_resolution(type_id, type_name)
+            }
+            // outside of a scope there's nothing to tie a scoped instance to, it's
+            // built fresh like a transient instead of leaking into the root cache
+            (Lifetime::Transient | Lifetime::Scoped, _) => {
+                self.record_miss(type_id);
+                self.create(&type_id, type_name)
+            }
+        }
+    }
+
+    // kept out of `resolve_any`, which sits on every level of a nested resolution
+    fn resolve_root(&self, type_id: TypeId, type_name: &'static str) -> Result<Rc<dyn Any>, Error> {
+        let cached = match self.shared.get(&type_id) {
+            Some(service) => Some(service.clone()),
+            None => self.services.borrow().get(&type_id).cloned(),
+        };
+
+        match cached {
+            Some(service) if service.is::<UnderConstruction>() => {
+                Err(Error::SingletonReentrancy(type_name.to_string()))
+            }
+            Some(service) => {
+                self.record_hit(type_id);
+                Ok(service)
+            }
+            None => {
+                self.ensure_cacheable(type_name)?;
+                self.record_miss(type_id);
+
+                self.services
+                    .borrow_mut()
+                    .insert(type_id, Rc::new(UnderConstruction));
+                let guard = OnUnwind(|| {
+                    self.services.borrow_mut().remove(&type_id);
+                });
+                let service = self.create(&type_id, type_name);
+                guard.disarm();
+
+                let mut services = self.services.borrow_mut();
+                match service {
+                    Ok(service) => {
+                        services.insert(type_id, service.clone());
+                        self.creation_order
+                            .borrow_mut()
+                            .push(RootEntry::Service(type_id));
+                        Ok(service)
+                    }
+                    Err(error) => {
+                        services.remove(&type_id);
+                        Err(error)
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve_within_resolution(
+        &self,
+        type_id: TypeId,
+        type_name: &'static str,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let cached = self.resolution_cache.borrow().get(&type_id).cloned();
+
+        match cached {
+            Some(service) => {
+                self.record_hit(type_id);
+                Ok(service)
+            }
+            None => {
+                self.record_miss(type_id);
+                let service = self.create(&type_id, type_name)?;
+
+                // outside of a resolution there's nobody to share it with
+                if !self.stack.borrow().is_empty() {
+                    self.resolution_cache
+                        .borrow_mut()
+                        .insert(type_id, service.clone());
+                }
+
+                Ok(service)
+            }
+        }
+    }
+}
+
+impl ScopedServiceProvider {
+    /// Creates a new scope over `provider`, same as
+    /// [`ServiceProvider::create_scope`]. The provider has to live in an `Rc`
+    /// because every scope shares it with the others.
+    pub fn from_provider(provider: Rc<ServiceProvider>) -> Self {
+        provider.remember_rc(&provider);
+        let id = provider.scope_created();
+
+        ScopedServiceProvider {
+            provider,
+            id,
+            is_view: false,
+            services: Rc::new(RefCell::new(HashMap::new())),
+            inherited: Rc::new([]),
+            locals: Rc::new(RefCell::new(HashMap::new())),
+            creation_order: Rc::new(RefCell::new(Vec::new())),
+            keyed: RefCell::new(HashMap::new()),
+            disposers: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Creates a new independent scope over the same provider.
+    pub fn create_scope(&self) -> ScopedServiceProvider {
+        Self::from_provider(self.provider.clone())
+    }
+
+    /// Creates a child scope that reuses the scoped instances already cached by
+    /// this scope (and the scopes it inherits from) instead of building its
+    /// own. Instances the child builds itself stay in the child.
+    pub fn create_inheriting_scope(&self) -> ScopedServiceProvider {
+        let mut scope = self.create_scope();
+        scope.inherited = std::iter::once(self.services.clone())
+            .chain(self.inherited.iter().cloned())
+            .collect();
+        scope
+    }
+
+    /// Adds `value` to this scope only, after the provider was built. `get::<T>()`
+    /// on this scope, and every factory it runs, resolves to the value, even
+    /// when `T` is also registered in the collection.
+    ///
+    /// Locals are invisible to the root provider and to every other scope,
+    /// including child scopes, and are dropped together with the scope.
+    pub fn add_local<T: Any>(&self, value: T) {
+        self.locals
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(value));
+    }
+
+    /// Makes `get_boxed::<T>()` in this scope, and in the factories it runs,
+    /// return `instance` instead of the root singleton, e.g. a dry-run
+    /// implementation. The root provider and other scopes keep the real one.
+    pub fn override_singleton_boxed<T: ?Sized + 'static>(&self, instance: Box<T>) {
+        self.add_local::<Box<T>>(instance);
+    }
+
+    /// Same as `add_local`, for values that have to be in place before
+    /// anything in the scope resolves them, like the current request. Fails
+    /// with [`Error::AlreadyResolved`] if `T` was already resolved or seeded in
+    /// this scope, instead of silently replacing the instance others got.
+    pub fn seed<T: Any>(&self, value: T) -> Result<&Self, Error> {
+        let type_id = TypeId::of::<T>();
+
+        if self.services.borrow().contains_key(&type_id)
+            || self.locals.borrow().contains_key(&type_id)
+        {
+            return Err(Error::AlreadyResolved(
+                std::any::type_name::<T>().to_string(),
+            ));
+        }
+
+        self.add_local(value);
+        Ok(self)
+    }
+
+    pub(crate) fn active(&self) -> ActiveScope {
+        ActiveScope {
+            services: self.services.clone(),
+            inherited: self.inherited.clone(),
+            locals: self.locals.clone(),
+            creation_order: self.creation_order.clone(),
+            id: self.id,
+        }
+    }
+
+    /// Registers `dispose` to run when the scope is dropped, in reverse order of
+    /// registration.
+    pub fn on_dispose(&self, dispose: impl FnOnce() + 'static) {
+        self.disposers.borrow_mut().push(Box::new(dispose));
+    }
+
+    pub fn provider_weak(&self) -> Weak<ServiceProvider> {
+        Rc::downgrade(&self.provider)
+    }
+
+    pub fn get_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Rc<Box<T>>, Error> {
+        self.get::<Box<T>>()
+    }
+
+    // the fallback instance is handed out as-is and never cached. it only
+    // stands in for an unregistered `T`, any other failure is returned
+    pub fn get_or_default_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        fallback: impl FnOnce() -> Box<T>,
+    ) -> Result<Rc<Box<T>>, Error> {
+        match self.get_boxed::<T>() {
+            Err(Error::ServiceNotFound(_))
+                if !self
+                    .provider
+                    .collection
+                    .services
+                    .contains_key(&TypeId::of::<Box<T>>()) =>
+            {
+                Ok(Rc::new(fallback()))
+            }
+            service => service,
+        }
+    }
+
+    #[allow(clippy::needless_maybe_sized)]
+    pub fn get<T: ?Sized + Unboxed>(&self) -> Result<Rc<T>, Error> {
+        let type_id = TypeId::of::<T>();
+        let type_name = std::any::type_name::<T>();
+
+        if let Some(local) = self.locals.borrow().get(&type_id).cloned() {
+            return downcast_service(local, type_name);
+        }
+
+        // every factory run for this resolution sees the scope, whatever the
+        // lifetime of the requested service
+        let scopes = &self.provider.scopes;
+        scopes.borrow_mut().push(self.active());
+        let guard = OnUnwind(|| {
+            scopes.borrow_mut().pop();
+        });
+        let service = self.provider.resolve_any(type_id, type_name);
+        guard.disarm();
+        scopes.borrow_mut().pop();
+
+        downcast_service(service?, type_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Greeter {
+        fn greet(&self) -> String;
+    }
+
+    struct RealGreeter;
+    impl Greeter for RealGreeter {
+        fn greet(&self) -> String {
+            "real".to_string()
+        }
+    }
+
+    struct MockGreeter;
+    impl Greeter for MockGreeter {
+        fn greet(&self) -> String {
+            "mock".to_string()
+        }
+    }
+
+    #[test]
+    fn mock_boxed_replaces_registration_with_shared_instance() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+        collection.mock_boxed::<dyn Greeter>(Box::new(MockGreeter));
+
+        let provider = collection.build();
+        let first = provider.get_boxed::<dyn Greeter>().unwrap();
+        let second = provider.get_boxed::<dyn Greeter>().unwrap();
+
+        assert_eq!(first.greet(), "mock");
+        assert!(Rc::ptr_eq(&first, &second));
+    }
+
+    trait Link {}
+
+    struct Node(#[allow(dead_code)] Option<Rc<Box<dyn Link>>>);
+    impl Link for Node {}
+
+    fn chain(length: usize) -> ServiceCollection {
+        let remaining = Rc::new(Cell::new(length));
+        let mut collection = ServiceCollection::new();
+
+        collection.try_add_transient_boxed::<dyn Link, _>(move |provider| {
+            if remaining.get() == 0 {
+                return Ok(Box::new(Node(None)));
+            }
+
+            remaining.set(remaining.get() - 1);
+            Ok(Box::new(Node(Some(provider.get_boxed::<dyn Link>()?))))
+        });
+
+        collection
+    }
+
+    #[test]
+    fn deep_chain_returns_depth_error_instead_of_overflowing() {
+        let provider = chain(500).build();
+
+        assert!(matches!(
+            provider.get_boxed::<dyn Link>(),
+            Err(Error::ResolutionDepthExceeded(_))
+        ));
+        assert!(provider.stack.borrow().is_empty());
+    }
+
+    #[test]
+    fn chain_within_depth_limit_resolves() {
+        let mut collection = chain(500);
+        collection.set_max_resolution_depth(1000);
+
+        assert!(collection.build().get_boxed::<dyn Link>().is_ok());
+    }
+
+    #[test]
+    fn current_depth_counts_the_factories_above_the_caller() {
+        let depths = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+
+        let outer = depths.clone();
+        collection.add_transient_boxed::<u32, _>(move |provider| {
+            outer.borrow_mut().push(provider.current_depth());
+            provider.get_boxed::<u64>().unwrap();
+            Box::new(0)
+        });
+        let inner = depths.clone();
+        collection.add_transient_boxed::<u64, _>(move |provider| {
+            inner.borrow_mut().push(provider.current_depth());
+            Box::new(0)
+        });
+
+        let provider = collection.build();
+        provider.get_boxed::<u32>().unwrap();
+
+        assert_eq!(*depths.borrow(), vec![0, 1]);
+        assert_eq!(provider.current_depth(), 0);
+    }
+
+    #[test]
+    fn current_chain_lists_the_types_being_built() {
+        let chain = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+
+        collection.add_transient_boxed::<u32, _>(|provider| {
+            provider.get_boxed::<u64>().unwrap();
+            Box::new(0)
+        });
+        let seen = chain.clone();
+        collection.add_singleton_boxed::<u64, _>(move |provider| {
+            *seen.borrow_mut() = provider.current_chain();
+            Box::new(0)
+        });
+
+        let provider = collection.build();
+        provider.get_boxed::<u32>().unwrap();
+
+        assert_eq!(
+            *chain.borrow(),
+            vec![
+                std::any::type_name::<Box<u32>>(),
+                std::any::type_name::<Box<u64>>()
+            ]
+        );
+        assert!(provider.current_chain().is_empty());
+    }
+
+    #[test]
+    fn denied_transients_fail_inside_singleton_factories() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .deny_transient_in_singleton()
+            .add_transient_boxed::<u16, _>(|_| Box::new(1))
+            .try_add_singleton_boxed::<u32, _>(|provider| {
+                Ok(Box::new(**provider.get_boxed::<u16>()? as u32))
+            })
+            .try_add_scoped_boxed::<u64, _>(|provider| {
+                Ok(Box::new(**provider.get_boxed::<u16>()? as u64))
+            });
+
+        let provider = collection.build();
+
+        assert!(provider.get_boxed::<u16>().is_ok());
+        assert!(provider.get_boxed::<u64>().is_ok());
+        assert!(matches!(
+            provider.get_boxed::<u32>(),
+            Err(Error::TransientCapturedBySingleton { .. })
+        ));
+    }
+
+    #[test]
+    fn self_resolving_singleton_reports_reentrancy() {
+        let mut collection = ServiceCollection::new();
+        collection.try_add_singleton_boxed::<dyn Link, _>(|provider| {
+            Ok(Box::new(Node(Some(provider.get_boxed::<dyn Link>()?))))
+        });
+
+        let provider = collection.build();
+
+        assert!(matches!(
+            provider.get_boxed::<dyn Link>(),
+            Err(Error::SingletonReentrancy(_))
+        ));
+        assert!(provider.cached_types().is_empty());
+    }
+
+    struct StorageConfig {
+        remote: bool,
+    }
+
+    trait Storage {
+        fn kind(&self) -> &'static str;
+    }
+
+    struct RemoteStorage;
+    impl Storage for RemoteStorage {
+        fn kind(&self) -> &'static str {
+            "remote"
+        }
+    }
+
+    struct LocalStorage;
+    impl Storage for LocalStorage {
+        fn kind(&self) -> &'static str {
+            "local"
+        }
+    }
+
+    #[test]
+    fn singleton_factory_branches_on_a_singleton_registered_later() {
+        let built = Rc::new(RefCell::new(Vec::new()));
+        let mut collection = ServiceCollection::new();
+
+        let log = built.clone();
+        collection.add_singleton_boxed::<dyn Storage, _>(move |provider| {
+            let config = provider.get_boxed::<StorageConfig>().unwrap();
+            log.borrow_mut().push("storage");
+
+            if config.remote {
+                Box::new(RemoteStorage)
+            } else {
+                Box::new(LocalStorage)
+            }
+        });
+        let log = built.clone();
+        collection.add_singleton_boxed::<StorageConfig, _>(move |_| {
+            log.borrow_mut().push("config");
+            Box::new(StorageConfig { remote: true })
+        });
+
+        let provider = collection.build();
+
+        assert_eq!(
+            provider.get_boxed::<dyn Storage>().unwrap().kind(),
+            "remote"
+        );
+        assert_eq!(*built.borrow(), vec!["config", "storage"]);
+        assert_eq!(
+            *provider.creation_order.borrow(),
+            vec![
+                RootEntry::Service(TypeId::of::<Box<StorageConfig>>()),
+                RootEntry::Service(TypeId::of::<Box<dyn Storage>>())
+            ]
+        );
+    }
+
+    #[test]
+    fn provider_weak_does_not_keep_the_provider_alive() {
+        let provider = Rc::new(ServiceCollection::new().build());
+        let scope = provider.create_scope();
+        assert_eq!(Rc::strong_count(&provider), 2);
+
+        let weak = scope.provider_weak();
+        assert_eq!(Rc::strong_count(&provider), 2);
+
+        drop(scope);
+        assert_eq!(Rc::strong_count(&provider), 1);
+
+        drop(provider);
+        assert!(weak.upgrade().is_none());
+    }
+
+    trait Session {}
+    struct SessionImpl;
+    impl Session for SessionImpl {}
+
+    trait Request {}
+
+    #[test]
+    fn failed_scoped_resolution_rolls_back_the_scope_cache() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+        collection.try_add_scoped_boxed::<dyn Request, _>(|provider| {
+            provider.get_boxed::<dyn Session>()?;
+            Err(Error::Unknown("request rejected".to_string()))
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+
+        assert!(scope.get_boxed::<dyn Request>().is_err());
+        assert!(scope.services.borrow().is_empty());
+        assert!(provider.services.borrow().is_empty());
+
+        scope.get_boxed::<dyn Session>().unwrap();
+        assert_eq!(scope.services.borrow().len(), 1);
+    }
+
+    #[test]
+    fn get_or_default_boxed_falls_back_without_caching() {
+        let provider = ServiceCollection::new().build();
+
+        let first = provider
+            .get_or_default_boxed::<dyn Greeter>(|| Box::new(MockGreeter))
+            .unwrap();
+        let second = provider
+            .get_or_default_boxed::<dyn Greeter>(|| Box::new(MockGreeter))
+            .unwrap();
+
+        assert_eq!(first.greet(), "mock");
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert!(provider.get_boxed::<dyn Greeter>().is_err());
+    }
+
+    #[test]
+    fn get_or_default_boxed_returns_failures_of_registered_services() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .try_add_scoped_boxed::<dyn Greeter, _>(|_| Err(Error::Unknown("offline".to_string())))
+            .try_add_transient_boxed::<dyn Session, _>(|provider| {
+                provider.get_boxed::<dyn Request>()?;
+                Ok(Box::new(SessionImpl))
+            });
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+
+        assert!(matches!(
+            provider.get_or_default_boxed::<dyn Greeter>(|| Box::new(MockGreeter)),
+            Err(Error::Unknown(_))
+        ));
+        assert!(matches!(
+            scope.get_or_default_boxed::<dyn Greeter>(|| Box::new(MockGreeter)),
+            Err(Error::Unknown(_))
+        ));
+        assert!(matches!(
+            provider.get_or_default_boxed::<dyn Session>(|| Box::new(SessionImpl)),
+            Err(Error::ServiceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn get_if_boxed_resolves_only_when_the_condition_holds() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_boxed::<dyn Greeter, _>(|_| Box::new(MockGreeter));
+        let provider = collection.build();
+
+        assert!(
+            provider
+                .get_if_boxed::<dyn Greeter>(false)
+                .unwrap()
+                .is_none()
+        );
+        assert!(provider.get_if_boxed::<u32>(false).unwrap().is_none());
+        assert!(
+            provider
+                .get_if_boxed::<dyn Greeter>(true)
+                .unwrap()
+                .is_some()
+        );
+        assert!(matches!(
+            provider.get_if_boxed::<u32>(true),
+            Err(Error::ServiceNotFound(_))
+        ));
+        assert_eq!(provider.stats::<Box<dyn Greeter>>().misses, 1);
+    }
+
+    #[test]
+    fn sealed_provider_rejects_new_cache_entries() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_singleton_boxed::<u64, _>(|_| Box::new(2));
+        collection.add_transient_boxed::<u16, _>(|_| Box::new(3));
+
+        let provider = collection.build();
+        provider.get_boxed::<u32>().unwrap();
+        provider.seal();
+
+        assert!(provider.get_boxed::<u32>().is_ok());
+        assert!(provider.get_boxed::<u16>().is_ok());
+        assert!(matches!(
+            provider.get_boxed::<u64>(),
+            Err(Error::ProviderSealed(_))
+        ));
+    }
+
+    #[test]
+    fn scoped_services_resolved_from_the_root_are_not_cached() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+
+        let provider = Rc::new(collection.build());
+        let first = provider.get_boxed::<dyn Greeter>().unwrap();
+        let second = provider.get_boxed::<dyn Greeter>().unwrap();
+
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert_eq!(provider.cached_instances(), 0);
+
+        let scope = provider.create_scope();
+        assert!(Rc::ptr_eq(
+            &scope.get_boxed::<dyn Greeter>().unwrap(),
+            &scope.get_boxed::<dyn Greeter>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn transients_resolved_from_a_scope_get_its_scoped_dependencies() {
+        struct Handler(Rc<Box<dyn Greeter>>);
+
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_scoped_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter))
+            .add_transient_boxed::<Handler, _>(|provider| {
+                Box::new(Handler(provider.get_boxed::<dyn Greeter>().unwrap()))
+            });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let handler = scope.get_boxed::<Handler>().unwrap();
+
+        assert!(Rc::ptr_eq(
+            &handler.0,
+            &scope.get_boxed::<dyn Greeter>().unwrap()
+        ));
+        assert!(provider.scopes.borrow().is_empty());
+    }
+
+    #[test]
+    fn cache_limit_rejects_instances_past_the_boundary() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_singleton_boxed::<u64, _>(|_| Box::new(2));
+        collection.add_singleton_boxed::<i32, _>(|_| Box::new(3));
+        collection.add_transient_boxed::<u16, _>(|_| Box::new(4));
+
+        let provider = collection.build();
+        provider.set_max_cached_instances(2);
+
+        provider.get_boxed::<u32>().unwrap();
+        provider.get_boxed::<u64>().unwrap();
+        assert_eq!(provider.cached_instances(), 2);
+
+        assert!(provider.get_boxed::<u32>().is_ok());
+        assert!(provider.get_boxed::<u16>().is_ok());
+        assert!(matches!(
+            provider.get_boxed::<i32>(),
+            Err(Error::CacheLimitReached(_))
+        ));
+        assert_eq!(provider.cached_instances(), 2);
+    }
+
+    #[derive(Debug)]
+    enum AppError {
+        Container(Error),
+    }
+
+    impl From<Error> for AppError {
+        fn from(error: Error) -> Self {
+            AppError::Container(error)
+        }
+    }
+
+    #[test]
+    fn get_map_boxed_returns_the_derived_value() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+
+        let provider = collection.build();
+
+        assert_eq!(
+            provider
+                .get_map_boxed::<dyn Greeter, _>(|greeter| greeter.greet().len())
+                .unwrap(),
+            4
+        );
+    }
+
+    struct Concrete;
+    impl Greeter for Concrete {
+        fn greet(&self) -> String {
+            "concrete".to_string()
+        }
+    }
+
+    #[test]
+    fn boxed_and_unboxed_keys_resolve_only_their_own_lookups() {
+        let unboxed = {
+            let mut collection = ServiceCollection::new();
+            collection.add_singleton::<Concrete, _>(|_| Box::new(Concrete));
+            collection.build()
+        };
+        let boxed = {
+            let mut collection = ServiceCollection::new();
+            collection.add_singleton_boxed::<Concrete, _>(|_| Box::new(Concrete));
+            collection.build()
+        };
+        let trait_object = {
+            let mut collection = ServiceCollection::new();
+            collection.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(Concrete));
+            collection.build()
+        };
+
+        assert!(unboxed.get::<Concrete>().is_ok());
+        assert!(unboxed.get_boxed::<Concrete>().is_err());
+        assert!(unboxed.get_boxed::<dyn Greeter>().is_err());
+
+        assert!(boxed.get::<Concrete>().is_err());
+        assert!(boxed.get_boxed::<Concrete>().is_ok());
+        assert!(boxed.get::<Box<Concrete>>().is_ok());
+        assert!(boxed.get_boxed::<dyn Greeter>().is_err());
+
+        assert!(trait_object.get::<Concrete>().is_err());
+        assert!(trait_object.get_boxed::<Concrete>().is_err());
+        assert!(trait_object.get_boxed::<dyn Greeter>().is_ok());
+        assert!(trait_object.get::<Box<dyn Greeter>>().is_ok());
+    }
+
+    #[test]
+    fn lookups_missing_only_the_box_explain_the_mismatch() {
+        let mut unboxed = ServiceCollection::new();
+        unboxed.add_singleton::<Concrete, _>(|_| Box::new(Concrete));
+        let mut boxed = ServiceCollection::new();
+        boxed.add_singleton_boxed::<Concrete, _>(|_| Box::new(Concrete));
+
+        let Err(Error::ServiceNotFound(message)) = unboxed.build().get_boxed::<Concrete>() else {
+            panic!("expected a missing service");
+        };
+        assert!(message.ends_with("resolve it with get)"));
+
+        let Err(Error::ServiceNotFound(message)) = boxed.build().get::<Concrete>() else {
+            panic!("expected a missing service");
+        };
+        assert!(message.ends_with("resolve it with get_boxed)"));
+    }
+
+    #[test]
+    fn factory_ref_exposes_the_erased_factory() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+
+        let factory = collection.factory_ref::<Box<dyn Greeter>>().unwrap();
+        let instance = factory(&ServiceProvider::default()).unwrap();
+
+        assert_eq!(
+            instance.downcast::<Box<dyn Greeter>>().unwrap().greet(),
+            "real"
+        );
+        assert!(collection.factory_ref::<Box<dyn Session>>().is_none());
+    }
+
+    #[test]
+    fn get_or_register_singleton_boxed_registers_missing_plugins_once() {
+        let provider = ServiceCollection::new().build();
+
+        let first = provider
+            .get_or_register_singleton_boxed::<dyn Greeter>(|_| Box::new(MockGreeter))
+            .unwrap();
+        let second = provider
+            .get_or_register_singleton_boxed::<dyn Greeter>(|_| Box::new(RealGreeter))
+            .unwrap();
+
+        assert_eq!(second.greet(), "mock");
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(Rc::ptr_eq(
+            &first,
+            &provider.get_boxed::<dyn Greeter>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn get_or_register_singleton_boxed_respects_the_sealed_cache() {
+        let provider = ServiceCollection::new().build();
+        provider.seal();
+
+        assert!(matches!(
+            provider.get_or_register_singleton_boxed::<dyn Greeter>(|_| Box::new(MockGreeter)),
+            Err(Error::ProviderSealed(_))
+        ));
+        assert_eq!(provider.cached_instances(), 0);
+    }
+
+    #[test]
+    fn get_or_register_singleton_boxed_keeps_registered_transients_transient() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter))
+            .try_add_transient_boxed::<u32, _>(|_| Err(Error::Unknown("offline".to_string())));
+        let provider = collection.build();
+
+        let first = provider
+            .get_or_register_singleton_boxed::<dyn Greeter>(|_| Box::new(MockGreeter))
+            .unwrap();
+        let second = provider
+            .get_or_register_singleton_boxed::<dyn Greeter>(|_| Box::new(MockGreeter))
+            .unwrap();
+
+        assert_eq!(first.greet(), "real");
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert!(matches!(
+            provider.get_or_register_singleton_boxed::<u32>(|_| Box::new(0)),
+            Err(Error::Unknown(_))
+        ));
+        assert_eq!(provider.cached_instances(), 0);
+    }
+
+    #[test]
+    fn get_into_converts_the_error() {
+        let provider = ServiceCollection::new().build();
+
+        assert!(matches!(
+            provider.get_into::<u32, AppError>(),
+            Err(AppError::Container(Error::ServiceNotFound(_)))
+        ));
+    }
+
+    struct RequestContext(&'static str);
+
+    #[test]
+    fn locals_are_visible_only_inside_their_scope() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<String, _>(|provider| {
+            Box::new(provider.get::<RequestContext>().unwrap().0.to_string())
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.add_local(RequestContext("req-1"));
+
+        assert_eq!(scope.get::<RequestContext>().unwrap().0, "req-1");
+        assert_eq!(**scope.get_boxed::<String>().unwrap(), "req-1");
+        assert!(provider.get::<RequestContext>().is_err());
+        assert!(scope.create_scope().get::<RequestContext>().is_err());
+    }
+
+    #[test]
+    fn transient_factories_run_from_a_scope_see_its_locals() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_boxed::<String, _>(|provider| {
+            Box::new(
+                provider
+                    .get::<RequestContext>()
+                    .map_or("none", |context| context.0)
+                    .to_string(),
+            )
+        });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.add_local(RequestContext("req-1"));
+
+        assert_eq!(**scope.get_boxed::<String>().unwrap(), "req-1");
+        assert_eq!(**provider.get_boxed::<String>().unwrap(), "none");
+    }
+
+    #[test]
+    fn overridden_singleton_stays_inside_the_scope() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter))
+            .add_transient_boxed::<String, _>(|provider| {
+                Box::new(provider.get_boxed::<dyn Greeter>().unwrap().greet())
+            });
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.override_singleton_boxed::<dyn Greeter>(Box::new(MockGreeter));
+
+        assert_eq!(scope.get_boxed::<dyn Greeter>().unwrap().greet(), "mock");
+        assert_eq!(**scope.get_boxed::<String>().unwrap(), "mock");
+        assert_eq!(**provider.get_boxed::<String>().unwrap(), "real");
+        assert_eq!(provider.get_boxed::<dyn Greeter>().unwrap().greet(), "real");
+        assert_eq!(
+            provider
+                .create_scope()
+                .get_boxed::<dyn Greeter>()
+                .unwrap()
+                .greet(),
+            "real"
+        );
+    }
+
+    #[test]
+    fn seed_rejects_types_already_resolved_in_the_scope() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        scope.seed(RequestContext("req-1")).unwrap();
+        scope.get_boxed::<dyn Session>().unwrap();
+
+        assert_eq!(scope.get::<RequestContext>().unwrap().0, "req-1");
+        assert!(matches!(
+            scope.seed(RequestContext("req-2")),
+            Err(Error::AlreadyResolved(_))
+        ));
+        assert!(matches!(
+            scope.seed::<Box<dyn Session>>(Box::new(SessionImpl)),
+            Err(Error::AlreadyResolved(_))
+        ));
+    }
+
+    #[test]
+    fn from_provider_creates_an_independent_scope() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+
+        let provider = Rc::new(collection.build());
+        let first = ScopedServiceProvider::from_provider(provider.clone());
+        let second = ScopedServiceProvider::from_provider(provider.clone());
+
+        assert_eq!(Rc::strong_count(&provider), 3);
+        assert!(!Rc::ptr_eq(
+            &first.get_boxed::<dyn Session>().unwrap(),
+            &second.get_boxed::<dyn Session>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn on_dispose_runs_in_reverse_order_when_scope_drops() {
+        let provider = Rc::new(ServiceCollection::new().build());
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let scope = provider.create_scope();
+        for step in 1..=3 {
+            let order = order.clone();
+            scope.on_dispose(move || order.borrow_mut().push(step));
+        }
+
+        assert!(order.borrow().is_empty());
+        drop(scope);
+        assert_eq!(*order.borrow(), vec![3, 2, 1]);
+    }
+
+    trait Connection {}
+    struct ConnectionImpl;
+    impl Connection for ConnectionImpl {}
+
+    trait Repositories {
+        fn connection(&self) -> &Rc<Box<dyn Connection>>;
+        fn shares_connection(&self) -> bool;
+    }
+
+    struct RepositoriesImpl(Rc<Box<dyn Connection>>, Rc<Box<dyn Connection>>);
+    impl Repositories for RepositoriesImpl {
+        fn connection(&self) -> &Rc<Box<dyn Connection>> {
+            &self.0
+        }
+
+        fn shares_connection(&self) -> bool {
+            Rc::ptr_eq(&self.0, &self.1)
+        }
+    }
+
+    #[test]
+    fn transient_cached_in_scope_is_shared_within_a_scope_only() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_cached_in_scope_boxed::<dyn Connection, _>(|_| Box::new(ConnectionImpl));
+
+        let provider = Rc::new(collection.build());
+        let first = provider.create_scope();
+        let second = provider.create_scope();
+
+        assert!(Rc::ptr_eq(
+            &first.get_boxed::<dyn Connection>().unwrap(),
+            &first.get_boxed::<dyn Connection>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &first.get_boxed::<dyn Connection>().unwrap(),
+            &second.get_boxed::<dyn Connection>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &provider.get_boxed::<dyn Connection>().unwrap(),
+            &provider.get_boxed::<dyn Connection>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn singletons_preferring_scope_are_cached_where_they_are_resolved() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<dyn Connection, _>(|_| Box::new(ConnectionImpl))
+            .prefer_scope_boxed::<dyn Connection>();
+
+        let provider = Rc::new(collection.build());
+        let root = provider.get_boxed::<dyn Connection>().unwrap();
+        assert!(Rc::ptr_eq(
+            &root,
+            &provider.get_boxed::<dyn Connection>().unwrap()
+        ));
+
+        let first = provider.create_scope();
+        let second = provider.create_scope();
+        let in_first = first.get_boxed::<dyn Connection>().unwrap();
+        let in_second = second.get_boxed::<dyn Connection>().unwrap();
+
+        assert!(Rc::ptr_eq(
+            &in_first,
+            &first.get_boxed::<dyn Connection>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(&in_first, &in_second));
+        assert!(!Rc::ptr_eq(&in_first, &root));
+        assert_eq!(provider.cached_instances(), 1);
+    }
+
+    #[test]
+    fn transient_shared_within_resolution_is_built_once_per_get() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_shared_within_resolution_boxed::<dyn Connection, _>(|_| {
+            Box::new(ConnectionImpl)
+        });
+        collection.add_transient_boxed::<dyn Repositories, _>(|provider| {
+            Box::new(RepositoriesImpl(
+                provider.get_boxed::<dyn Connection>().unwrap(),
+                provider.get_boxed::<dyn Connection>().unwrap(),
+            ))
+        });
+
+        let provider = collection.build();
+        let first = provider.get_boxed::<dyn Repositories>().unwrap();
+        let second = provider.get_boxed::<dyn Repositories>().unwrap();
+
+        assert!(first.shares_connection());
+        assert!(!Rc::ptr_eq(first.connection(), second.connection()));
+        assert!(provider.resolution_cache.borrow().is_empty());
+    }
+
+    #[test]
+    fn mismatched_factory_output_is_reported_and_not_cached() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton::<u32, _>(|_| Box::new("not a number"));
+
+        let provider = collection.build();
+        let Err(Error::FactoryTypeMismatch { registered }) = provider.get::<u32>() else {
+            panic!("expected a type mismatch");
+        };
+
+        assert_eq!(registered, std::any::type_name::<u32>());
+        assert_eq!(provider.cached_instances(), 0);
+    }
+
+    #[test]
+    fn disabled_registrations_keep_their_factory() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1))
+            .set_enabled::<Box<u32>>(false);
+
+        let disabled = collection.clone().build();
+        assert!(matches!(
+            disabled.get_boxed::<u32>(),
+            Err(Error::ServiceDisabled(_))
+        ));
+
+        collection.set_enabled::<Box<u32>>(true);
+        assert_eq!(**collection.build().get_boxed::<u32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn services_can_be_toggled_on_the_built_provider() {
+        let built = Rc::new(Cell::new(0));
+
+        let mut collection = ServiceCollection::new();
+        let count = built.clone();
+        collection.add_singleton_boxed::<u32, _>(move |_| {
+            count.set(count.get() + 1);
+            Box::new(1)
+        });
+
+        let provider = collection.build();
+        provider.get_boxed::<u32>().unwrap();
+
+        provider.set_enabled::<Box<u32>>(false);
+        assert!(matches!(
+            provider.get_boxed::<u32>(),
+            Err(Error::ServiceDisabled(_))
+        ));
+
+        provider.set_enabled::<Box<u32>>(true);
+        provider.get_boxed::<u32>().unwrap();
+        assert_eq!(built.get(), 1);
+
+        assert!(provider.evict::<Box<u32>>());
+        provider.get_boxed::<u32>().unwrap();
+        assert_eq!(built.get(), 2);
+    }
+
+    #[test]
+    fn get_owned_boxed_hands_out_transients_only() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter))
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1));
+
+        let provider = collection.build();
+        let greeter: Box<dyn Greeter> = provider.get_owned_boxed::<dyn Greeter>().unwrap();
+
+        assert_eq!(greeter.greet(), "real");
+        assert!(matches!(
+            provider.get_owned_boxed::<u32>(),
+            Err(Error::NotTransient(_))
+        ));
+    }
+
+    #[test]
+    fn singletons_can_be_registered_from_plain_functions() {
+        fn greeter(_: &ServiceProvider) -> Box<dyn Greeter> {
+            Box::new(RealGreeter)
+        }
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_fn_boxed(greeter);
+
+        let provider = collection.build();
+        let first = provider.get_boxed::<dyn Greeter>().unwrap();
+
+        assert_eq!(first.greet(), "real");
+        assert!(Rc::ptr_eq(
+            &first,
+            &provider.get_boxed::<dyn Greeter>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn configure_builds_the_registered_services() {
+        let provider = ServiceProvider::configure(|services| {
+            services.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+        });
+
+        assert_eq!(provider.get_boxed::<dyn Greeter>().unwrap().greet(), "real");
+    }
+
+    #[test]
+    fn len_counts_registrations_with_last_one_winning() {
+        let mut collection = ServiceCollection::new();
+        assert!(collection.is_empty());
+
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1))
+            .add_transient_boxed::<u64, _>(|_| Box::new(2))
+            .add_singleton_boxed::<u32, _>(|_| Box::new(3));
+
+        assert_eq!(collection.len(), 2);
+        assert!(!collection.is_empty());
+    }
+
+    #[test]
+    fn try_clone_lets_variants_diverge_from_a_shared_base() {
+        let mut base = ServiceCollection::new();
+        base.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+
+        let mut staging = base.try_clone().unwrap();
+        staging.mock_boxed::<dyn Greeter>(Box::new(MockGreeter));
+
+        assert_eq!(
+            base.build().get_boxed::<dyn Greeter>().unwrap().greet(),
+            "real"
+        );
+        assert_eq!(
+            staging.build().get_boxed::<dyn Greeter>().unwrap().greet(),
+            "mock"
+        );
+    }
+
+    #[test]
+    fn add_raw_registers_an_erased_factory() {
+        let mut collection = ServiceCollection::new();
+        collection.add_raw(
+            TypeId::of::<Box<dyn Greeter>>(),
+            Lifetime::Transient,
+            "plugin::Greeter",
+            Box::new(|_| Box::new(Box::new(RealGreeter) as Box<dyn Greeter>)),
+        );
+
+        let provider = collection.build();
+        assert_eq!(provider.get_boxed::<dyn Greeter>().unwrap().greet(), "real");
+        assert!(provider.get_by_name("plugin::Greeter").is_ok());
+    }
+
+    #[test]
+    fn providers_built_with_shared_reuse_its_singletons() {
+        let collection = || {
+            let mut collection = ServiceCollection::new();
+            collection.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(RealGreeter));
+            collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+            collection
+        };
+
+        let model: Rc<dyn Any> = Rc::new(Box::new(MockGreeter) as Box<dyn Greeter>);
+        let shared = Rc::new(HashMap::from([(TypeId::of::<Box<dyn Greeter>>(), model)]));
+
+        let blue = collection().build_with_shared(shared.clone());
+        let green = collection().build_with_shared(shared);
+
+        assert_eq!(blue.get_boxed::<dyn Greeter>().unwrap().greet(), "mock");
+        assert!(Rc::ptr_eq(
+            &blue.get_boxed::<dyn Greeter>().unwrap(),
+            &green.get_boxed::<dyn Greeter>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &blue.get_boxed::<u32>().unwrap(),
+            &green.get_boxed::<u32>().unwrap()
+        ));
+    }
+
+    #[test]
+    fn from_descriptors_registers_erased_factories() {
+        let collection = ServiceCollection::from_descriptors(vec![(
+            TypeId::of::<Box<dyn Greeter>>(),
+            ServiceDescriptor::new(
+                Lifetime::Singleton,
+                Rc::new(|_| Ok(Box::new(Box::new(RealGreeter) as Box<dyn Greeter>))),
+                std::any::type_name::<Box<dyn Greeter>>(),
+            ),
+        )]);
+
+        let provider = collection.build();
+        assert_eq!(provider.get_boxed::<dyn Greeter>().unwrap().greet(), "real");
+    }
+
+    #[test]
+    fn post_construct_runs_on_every_factory_output() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_transient_boxed::<u64, _>(|_| Box::new(1));
+
+        let log = seen.clone();
+        collection.add_post_construct(move |type_name, instance| {
+            log.borrow_mut().push(type_name);
+
+            if let Some(value) = instance.downcast_mut::<Box<u32>>() {
+                **value += 41;
+            }
+        });
+
+        let provider = collection.build();
+        assert_eq!(**provider.get_boxed::<u32>().unwrap(), 42);
+        assert_eq!(**provider.get_boxed::<u32>().unwrap(), 42);
+        assert_eq!(**provider.get_boxed::<u64>().unwrap(), 1);
+
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                std::any::type_name::<Box<u32>>(),
+                std::any::type_name::<Box<u64>>()
+            ]
+        );
+    }
+
+    #[test]
+    fn inheriting_scope_reuses_parent_scoped_instances() {
+        let mut collection = ServiceCollection::new();
+        collection.add_scoped_boxed::<dyn Session, _>(|_| Box::new(SessionImpl));
+        collection.add_scoped_boxed::<dyn Connection, _>(|_| Box::new(ConnectionImpl));
+
+        let provider = Rc::new(collection.build());
+        let request = provider.create_scope();
+        let session = request.get_boxed::<dyn Session>().unwrap();
+
+        let inheriting = request.create_inheriting_scope();
+        assert!(Rc::ptr_eq(
+            &session,
+            &inheriting.get_boxed::<dyn Session>().unwrap()
+        ));
+
+        inheriting.get_boxed::<dyn Connection>().unwrap();
+        assert!(request.services.borrow().len() == 1);
+
+        let independent = request.create_scope();
+        assert!(!Rc::ptr_eq(
+            &session,
+            &independent.get_boxed::<dyn Session>().unwrap()
+        ));
     }
 }
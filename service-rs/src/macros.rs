@@ -0,0 +1,269 @@
+/// Builds a [`ServiceCollection`](crate::ServiceCollection) from a list of
+/// `lifetime service => implementation;` entries.
+///
+/// The implementation is either an expression evaluated on every construction,
+/// or a closure taking the provider:
+///
+/// ```
+/// use service_rs::services;
+///
+/// trait Greeter {}
+/// struct GreeterImpl;
+/// impl Greeter for GreeterImpl {}
+///
+/// trait Handler {}
+/// struct HandlerImpl(std::rc::Rc<Box<dyn Greeter>>);
+/// impl Handler for HandlerImpl {}
+///
+/// let collection = services! {
+///     singleton dyn Greeter => GreeterImpl;
+///     scoped dyn Handler => |provider| HandlerImpl(provider.get_boxed::<dyn Greeter>().unwrap());
+/// };
+/// ```
+#[macro_export]
+macro_rules! services {
+    (@register $collection:ident;) => {};
+    (@register $collection:ident; $lifetime:ident $service:ty => |$provider:pat_param| $body:expr; $($rest:tt)*) => {
+        $crate::services!(@add $collection $lifetime $service, |$provider| Box::new($body));
+        $crate::services!(@register $collection; $($rest)*);
+    };
+    (@register $collection:ident; $lifetime:ident $service:ty => $implementation:expr; $($rest:tt)*) => {
+        $crate::services!(@add $collection $lifetime $service, |_| Box::new($implementation));
+        $crate::services!(@register $collection; $($rest)*);
+    };
+    (@add $collection:ident singleton $service:ty, $factory:expr) => {
+        $collection.add_singleton_boxed::<$service, _>($factory);
+    };
+    (@add $collection:ident scoped $service:ty, $factory:expr) => {
+        $collection.add_scoped_boxed::<$service, _>($factory);
+    };
+    (@add $collection:ident transient $service:ty, $factory:expr) => {
+        $collection.add_transient_boxed::<$service, _>($factory);
+    };
+    ($($body:tt)*) => {{
+        let mut collection = $crate::ServiceCollection::new();
+        $crate::services!(@register collection; $($body)*);
+        collection
+    }};
+}
+
+/// Registers `implementation` under its concrete type and exposes it as the
+/// trait object too, with the same lifetime: `get_boxed::<dyn Trait>()` and
+/// `get::<Implementation>()` hand out the same instance (within a scope for
+/// scoped services, a fresh one per `get` for transients).
+///
+/// The instance is built once with `Default::default()`. A `Box<dyn Trait>`
+/// can't borrow it, so the trait object holds its own `Rc<Implementation>`,
+/// which means the trait has to be implemented for `Rc<Implementation>`.
+///
+/// ```
+/// use std::rc::Rc;
+/// use service_rs::{ServiceCollection, register_impl};
+///
+/// trait Greeter {}
+/// #[derive(Default)]
+/// struct GreeterImpl;
+/// impl Greeter for Rc<GreeterImpl> {}
+///
+/// let mut collection = ServiceCollection::new();
+/// register_impl!(collection, dyn Greeter, GreeterImpl, singleton);
+///
+/// let provider = collection.build();
+/// assert!(provider.get_boxed::<dyn Greeter>().is_ok());
+/// assert!(provider.get::<GreeterImpl>().is_ok());
+/// ```
+#[macro_export]
+macro_rules! register_impl {
+    ($collection:expr, $service:ty, $implementation:ty, singleton) => {
+        $collection
+            .add_singleton_shared(|_| <$implementation as ::core::default::Default>::default())
+            .also_as_boxed::<$service>(|shared| ::std::boxed::Box::new(shared));
+    };
+    ($collection:expr, $service:ty, $implementation:ty, scoped) => {
+        $crate::register_impl!(@add $collection, $service, $implementation, try_add_scoped_boxed, add_scoped)
+    };
+    ($collection:expr, $service:ty, $implementation:ty, transient) => {
+        $crate::register_impl!(@add $collection, $service, $implementation, try_add_transient_boxed, add_transient)
+    };
+    (@add $collection:expr, $service:ty, $implementation:ty, $try_add_boxed:ident, $add:ident) => {
+        $collection
+            .$add::<$implementation, _>(|_| {
+                ::std::boxed::Box::new(<$implementation as ::core::default::Default>::default())
+            })
+            .$try_add_boxed::<$service, _>(|provider| {
+                let shared = provider.get::<$implementation>()?;
+                ::core::result::Result::Ok(::std::boxed::Box::new(shared) as ::std::boxed::Box<$service>)
+            });
+    };
+}
+
+/// Implements [`InjectTarget`](crate::InjectTarget) for a struct whose listed
+/// fields are `Option<Rc<Box<T>>>`, each resolved with `get_boxed::<T>()` when
+/// it's still `None`.
+///
+/// ```
+/// use std::rc::Rc;
+/// use service_rs::{ServiceCollection, inject_target};
+///
+/// trait Greeter {}
+/// struct GreeterImpl;
+/// impl Greeter for GreeterImpl {}
+///
+/// #[derive(Default)]
+/// struct Page {
+///     title: String,
+///     greeter: Option<Rc<Box<dyn Greeter>>>,
+/// }
+/// inject_target!(Page { greeter: dyn Greeter });
+///
+/// let mut collection = ServiceCollection::new();
+/// collection.add_singleton_boxed::<dyn Greeter, _>(|_| Box::new(GreeterImpl));
+///
+/// let mut page = Page::default();
+/// collection.build().inject_fields(&mut page).unwrap();
+/// assert!(page.greeter.is_some());
+/// ```
+#[macro_export]
+macro_rules! inject_target {
+    ($target:ty { $($field:ident: $service:ty),* $(,)? }) => {
+        impl $crate::InjectTarget for $target {
+            fn inject_fields(
+                &mut self,
+                provider: &$crate::ServiceProvider,
+            ) -> ::core::result::Result<(), $crate::Error> {
+                $(provider.inject_field::<$service>(&mut self.$field)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Resolves the first of several services that is registered, adapting each
+/// to the common trait object after `=>` with the closure next to it, see
+/// [`ServiceProvider::get_first_available_boxed`](crate::ServiceProvider::get_first_available_boxed).
+///
+/// Trait objects of unrelated traits can't be converted into each other, so
+/// every adapter receives the resolved `Rc<Box<dyn Service>>` and wraps it
+/// into something implementing the common trait.
+///
+/// ```
+/// use std::rc::Rc;
+/// use service_rs::{ServiceCollection, try_resolve};
+///
+/// trait Cache {}
+/// trait PrimaryCache {}
+/// trait SecondaryCache {}
+///
+/// struct Redis;
+/// impl SecondaryCache for Redis {}
+///
+/// struct Primary(Rc<Box<dyn PrimaryCache>>);
+/// impl Cache for Primary {}
+/// struct Secondary(Rc<Box<dyn SecondaryCache>>);
+/// impl Cache for Secondary {}
+///
+/// let mut collection = ServiceCollection::new();
+/// collection.add_singleton_boxed::<dyn SecondaryCache, _>(|_| Box::new(Redis));
+/// let provider = collection.build();
+///
+/// let cache: Box<dyn Cache> = try_resolve!(
+///     provider => dyn Cache,
+///     dyn PrimaryCache => |cache| Box::new(Primary(cache)),
+///     dyn SecondaryCache => |cache| Box::new(Secondary(cache)),
+/// )
+/// .unwrap();
+/// ```
+#[macro_export]
+macro_rules! try_resolve {
+    ($provider:expr => $common:ty, $($service:ty => $adapter:expr),+ $(,)?) => {
+        $provider.get_first_available_boxed::<$common>(&[
+            $(&|provider: &$crate::ServiceProvider| {
+                provider
+                    .get_boxed::<$service>()
+                    .map(|service| ($adapter)(service) as ::std::boxed::Box<$common>)
+            }),+
+        ])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    trait First {}
+    struct FirstImpl;
+    impl First for FirstImpl {}
+
+    trait Second {}
+    struct SecondImpl;
+    impl Second for SecondImpl {}
+
+    trait Third {}
+    #[allow(dead_code)]
+    struct ThirdImpl(Rc<Box<dyn First>>);
+    impl Third for ThirdImpl {}
+
+    #[test]
+    fn services_registers_every_entry_with_its_lifetime() {
+        let provider = Rc::new(
+            services! {
+                singleton dyn First => FirstImpl;
+                transient dyn Second => SecondImpl;
+                scoped dyn Third => |provider| ThirdImpl(provider.get_boxed::<dyn First>().unwrap());
+            }
+            .build(),
+        );
+
+        assert!(Rc::ptr_eq(
+            &provider.get_boxed::<dyn First>().unwrap(),
+            &provider.get_boxed::<dyn First>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &provider.get_boxed::<dyn Second>().unwrap(),
+            &provider.get_boxed::<dyn Second>().unwrap()
+        ));
+
+        let scope = provider.create_scope();
+        assert!(Rc::ptr_eq(
+            &scope.get_boxed::<dyn Third>().unwrap(),
+            &scope.get_boxed::<dyn Third>().unwrap()
+        ));
+        assert!(!Rc::ptr_eq(
+            &scope.get_boxed::<dyn Third>().unwrap(),
+            &provider.create_scope().get_boxed::<dyn Third>().unwrap()
+        ));
+    }
+
+    trait Counter {
+        fn address(&self) -> usize;
+    }
+
+    #[derive(Default)]
+    struct CounterImpl;
+
+    impl Counter for Rc<CounterImpl> {
+        fn address(&self) -> usize {
+            Rc::as_ptr(self) as usize
+        }
+    }
+
+    #[test]
+    fn register_impl_shares_one_instance_between_both_keys() {
+        let mut collection = crate::ServiceCollection::new();
+        register_impl!(collection, dyn Counter, CounterImpl, singleton);
+        let provider = Rc::new(collection.build());
+
+        let concrete = provider.get::<CounterImpl>().unwrap();
+        let view = provider.get_boxed::<dyn Counter>().unwrap();
+        assert_eq!(view.address(), Rc::as_ptr(&concrete) as usize);
+
+        let mut collection = crate::ServiceCollection::new();
+        register_impl!(collection, dyn Counter, CounterImpl, scoped);
+        let provider = Rc::new(collection.build());
+
+        let scope = provider.create_scope();
+        let concrete = scope.get::<CounterImpl>().unwrap();
+        let view = scope.get_boxed::<dyn Counter>().unwrap();
+        assert_eq!(view.address(), Rc::as_ptr(&concrete) as usize);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    ops::Deref,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, ServiceProvider, downcast_service};
+
+// instances with their expiry, by type and caller key. `None` never expires,
+// for a ttl reaching past what an `Instant` can represent
+type Memoized = HashMap<(TypeId, String), (Option<Instant>, Rc<dyn Any>)>;
+
+/// Wraps a provider to memoize transient results by a key the caller picks,
+/// for expensive services built per input (a compiled template, a parsed
+/// schema, ...). Everything else resolves through the wrapped provider.
+#[derive(Debug)]
+pub struct MemoizingProvider {
+    provider: Rc<ServiceProvider>,
+    memoized: RefCell<Memoized>,
+}
+
+impl MemoizingProvider {
+    pub fn new(provider: Rc<ServiceProvider>) -> Self {
+        MemoizingProvider {
+            provider,
+            memoized: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the `Box<T>` memoized under `key` if it hasn't expired yet,
+    /// otherwise resolves a new one and keeps it for `ttl`. Expired entries
+    /// are dropped when they're looked up again or by `purge_expired`.
+    pub fn get_memoized_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        key: String,
+        ttl: Duration,
+    ) -> Result<Rc<Box<T>>, Error> {
+        let type_name = std::any::type_name::<Box<T>>();
+        let entry = (TypeId::of::<Box<T>>(), key);
+
+        let cached = self
+            .memoized
+            .borrow()
+            .get(&entry)
+            .filter(|(expires_at, _)| expires_at.is_none_or(|at| Instant::now() < at))
+            .map(|(_, service)| service.clone());
+
+        if let Some(service) = cached {
+            return downcast_service(service, type_name);
+        }
+
+        // the factory may resolve through this wrapper too, don't hold the borrow
+        let service = self.provider.get_boxed::<T>()?;
+        self.memoized
+            .borrow_mut()
+            .insert(entry, (Instant::now().checked_add(ttl), service.clone()));
+
+        Ok(service)
+    }
+
+    /// Drops every expired entry.
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        self.memoized
+            .borrow_mut()
+            .retain(|_, (expires_at, _)| expires_at.is_none_or(|at| now < at));
+    }
+}
+
+impl Deref for MemoizingProvider {
+    type Target = ServiceProvider;
+
+    fn deref(&self) -> &Self::Target {
+        &self.provider
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time::Duration};
+
+    use super::MemoizingProvider;
+    use crate::ServiceCollection;
+
+    #[test]
+    fn memoized_instances_are_kept_per_key_until_they_expire() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_boxed::<String, _>(|_| Box::new("compiled".to_string()));
+
+        let provider = MemoizingProvider::new(Rc::new(collection.build()));
+        let hour = Duration::from_secs(3600);
+
+        let home = provider
+            .get_memoized_boxed::<String>("home".into(), hour)
+            .unwrap();
+        let about = provider
+            .get_memoized_boxed::<String>("about".into(), hour)
+            .unwrap();
+        assert!(Rc::ptr_eq(
+            &home,
+            &provider
+                .get_memoized_boxed::<String>("home".into(), hour)
+                .unwrap()
+        ));
+        assert!(!Rc::ptr_eq(&home, &about));
+
+        let expired = provider
+            .get_memoized_boxed::<String>("expired".into(), Duration::ZERO)
+            .unwrap();
+        assert!(!Rc::ptr_eq(
+            &expired,
+            &provider
+                .get_memoized_boxed::<String>("expired".into(), Duration::ZERO)
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn unbounded_ttls_never_expire() {
+        let mut collection = ServiceCollection::new();
+        collection.add_transient_boxed::<String, _>(|_| Box::new("compiled".to_string()));
+
+        let provider = MemoizingProvider::new(Rc::new(collection.build()));
+        let home = provider
+            .get_memoized_boxed::<String>("home".into(), Duration::MAX)
+            .unwrap();
+        provider.purge_expired();
+
+        assert!(Rc::ptr_eq(
+            &home,
+            &provider
+                .get_memoized_boxed::<String>("home".into(), Duration::MAX)
+                .unwrap()
+        ));
+    }
+}
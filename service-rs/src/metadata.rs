@@ -0,0 +1,63 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{ServiceCollection, ServiceProvider};
+
+impl ServiceCollection {
+    /// Same as `add_singleton_boxed`, with metadata attached to the
+    /// registration for tooling, e.g. `[("tag", "public-api".to_string())]`.
+    /// Read it back with [`ServiceCollection::metadata_of`].
+    pub fn add_singleton_boxed_with_meta<T: ?Sized + 'static, F>(
+        &mut self,
+        metadata: impl IntoIterator<Item = (&'static str, String)>,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        self.add_singleton_boxed::<T, _>(factory);
+
+        if let Some(descriptor) = self.services.get_mut(&TypeId::of::<Box<T>>()) {
+            descriptor.metadata = metadata.into_iter().collect();
+        }
+
+        self
+    }
+
+    /// The metadata attached to the registration of `T` (`Box<dyn Trait>` for
+    /// boxed registrations), `None` if `T` isn't registered. Registrations made
+    /// without metadata have an empty map.
+    pub fn metadata_of<T: Any>(&self) -> Option<&HashMap<&'static str, String>> {
+        self.services
+            .get(&TypeId::of::<T>())
+            .map(|descriptor| &descriptor.metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ServiceCollection;
+
+    trait Search {}
+
+    struct Experimental;
+    impl Search for Experimental {}
+
+    #[test]
+    fn metadata_is_kept_per_registration() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed_with_meta::<dyn Search, _>(
+                [("stage", "experimental".to_string())],
+                |_| Box::new(Experimental),
+            )
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1));
+
+        let metadata = collection.metadata_of::<Box<dyn Search>>().unwrap();
+        assert_eq!(metadata["stage"], "experimental");
+        assert!(collection.metadata_of::<Box<u32>>().unwrap().is_empty());
+        assert!(collection.metadata_of::<Box<u64>>().is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::{
+    Error, Lifetime, RootEntry, ScopedServiceProvider, ServiceCollection, ServiceDescriptor,
+    ServiceProvider, downcast_service,
+};
+
+// one of possibly many implementations registered for the same service type,
+// kept apart from the last-wins registrations that `get` resolves.
+#[derive(Debug, Clone)]
+pub(crate) struct OrderedDescriptor {
+    pub(crate) order: i32,
+    pub(crate) descriptor: ServiceDescriptor,
+}
+
+// key of the members of `Set` resolved as `T`, never instantiated
+struct SetMember<Set, T: ?Sized>(PhantomData<Set>, PhantomData<T>);
+
+impl ServiceCollection {
+    /// Adds one more singleton implementation of `T`, resolved together with
+    /// the others through `get_all_boxed::<T>()`, ascending by `order` and by
+    /// registration order on ties.
+    ///
+    /// These registrations are not visible to `get_boxed::<T>()`.
+    pub fn add_singleton_ordered_boxed<T: ?Sized + 'static, F>(
+        &mut self,
+        order: i32,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        let descriptor = ServiceDescriptor::new(
+            Lifetime::Singleton,
+            Rc::new(move |provider| Ok(Box::new(factory(provider)) as Box<dyn Any>)),
+            std::any::type_name::<Box<T>>(),
+        );
+
+        self.enumerables
+            .entry(TypeId::of::<Box<T>>())
+            .or_default()
+            .push(OrderedDescriptor { order, descriptor });
+
+        self
+    }
+
+    /// Adds a singleton implementation of `T` to the set named by the marker
+    /// type `Set`, resolved together with the other members through
+    /// `get_set_boxed::<Set, T>()` in registration order.
+    ///
+    /// Rust can't enumerate the implementations of a trait, so sets are how
+    /// related services (the stages of a pipeline, the handlers of a plugin,
+    /// ...) are grouped explicitly. Members are only visible through their set.
+    pub fn register_in_set_boxed<Set: 'static, T: ?Sized + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Box<T> + 'static,
+    {
+        let descriptor = ServiceDescriptor::new(
+            Lifetime::Singleton,
+            Rc::new(move |provider| Ok(Box::new(factory(provider)) as Box<dyn Any>)),
+            std::any::type_name::<Box<T>>(),
+        );
+
+        self.enumerables
+            .entry(TypeId::of::<SetMember<Set, Box<T>>>())
+            .or_default()
+            .push(OrderedDescriptor {
+                order: 0,
+                descriptor,
+            });
+
+        self
+    }
+
+    /// Registers the whole `get_all_boxed::<T>()` list as a singleton, so it can
+    /// be resolved (and injected) through `get::<Vec<Rc<Box<T>>>>()`.
+    pub fn add_collection_boxed<T: ?Sized + 'static>(&mut self) -> &mut Self {
+        self.register(
+            TypeId::of::<Vec<Rc<Box<T>>>>(),
+            std::any::type_name::<Vec<Rc<Box<T>>>>(),
+            Lifetime::Singleton,
+            Rc::new(|provider| Ok(Box::new(provider.get_all_boxed::<T>()?) as Box<dyn Any>)),
+        )
+    }
+}
+
+impl ServiceProvider {
+    pub fn get_all_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Vec<Rc<Box<T>>>, Error> {
+        self.get_all::<Box<T>>()
+    }
+
+    pub fn get_all<T: Any + 'static>(&self) -> Result<Vec<Rc<T>>, Error> {
+        self.resolve_members::<T>(TypeId::of::<T>())
+    }
+
+    /// Every member of the set `Set` registered with
+    /// `register_in_set_boxed::<Set, T>`, in registration order. An empty set
+    /// resolves to an empty list.
+    pub fn get_set_boxed<Set: 'static, T: ?Sized + Any + 'static>(
+        &self,
+    ) -> Result<Vec<Rc<Box<T>>>, Error> {
+        self.resolve_members::<Box<T>>(TypeId::of::<SetMember<Set, Box<T>>>())
+    }
+
+    fn resolve_members<T: Any + 'static>(&self, type_id: TypeId) -> Result<Vec<Rc<T>>, Error> {
+        let type_name = std::any::type_name::<T>();
+
+        self.ordered_indices(type_id)
+            .into_iter()
+            .map(|index| {
+                let service = self.resolve_enumerable(type_id, index)?;
+                downcast_service::<T>(service, type_name)
+            })
+            .collect()
+    }
+
+    /// Returns the first implementation of `T`, in `get_all_boxed` order, that
+    /// matches `pred`. Candidates are built one at a time, so the ones after
+    /// the match are never constructed; candidates that fail to build are
+    /// skipped.
+    ///
+    /// Useful when the choice depends on the service itself, e.g. picking the
+    /// first `dyn Handler` whose `can_handle(&msg)` returns true.
+    pub fn resolve_first_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        pred: impl Fn(&Box<T>) -> bool,
+    ) -> Option<Rc<Box<T>>> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+
+        self.ordered_indices(type_id).into_iter().find_map(|index| {
+            let service = self.resolve_enumerable(type_id, index).ok()?;
+            let service = downcast_service::<Box<T>>(service, type_name).ok()?;
+            pred(&service).then_some(service)
+        })
+    }
+
+    fn ordered_indices(&self, type_id: TypeId) -> Vec<usize> {
+        let Some(entries) = self.collection.enumerables.get(&type_id) else {
+            return Vec::new();
+        };
+
+        let mut indices = (0..entries.len()).collect::<Vec<_>>();
+        indices.sort_by_key(|index| entries[*index].order);
+        indices
+    }
+
+    pub(crate) fn resolve_enumerable(
+        &self,
+        type_id: TypeId,
+        index: usize,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let descriptor = &self.collection.enumerables[&type_id][index].descriptor;
+
+        self.record_dependency(descriptor.type_name);
+
+        if descriptor.lifetime == Lifetime::Transient {
+            return self.instantiate(descriptor, &type_id);
+        }
+
+        let cached = self
+            .enumerable_cache
+            .borrow()
+            .get(&(type_id, index))
+            .cloned();
+
+        match cached {
+            Some(service) => Ok(service),
+            None => {
+                self.ensure_cacheable(descriptor.type_name)?;
+                let service = self.instantiate(descriptor, &type_id)?;
+
+                self.enumerable_cache
+                    .borrow_mut()
+                    .insert((type_id, index), service.clone());
+                self.creation_order
+                    .borrow_mut()
+                    .push(RootEntry::Member(type_id, index));
+
+                Ok(service)
+            }
+        }
+    }
+}
+
+impl ScopedServiceProvider {
+    pub fn get_all_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Vec<Rc<Box<T>>>, Error> {
+        self.provider.get_all_boxed::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::ServiceCollection;
+
+    trait Middleware {
+        fn name(&self) -> &'static str;
+    }
+
+    struct Named(&'static str);
+    impl Middleware for Named {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn get_all_boxed_sorts_by_order_then_registration() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(10, |_| Box::new(Named("logging")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(0, |_| Box::new(Named("auth")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(10, |_| Box::new(Named("metrics")));
+
+        let provider = collection.build();
+        let first = provider.get_all_boxed::<dyn Middleware>().unwrap();
+        let second = provider.get_all_boxed::<dyn Middleware>().unwrap();
+
+        let names = first.iter().map(|m| m.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["auth", "logging", "metrics"]);
+        assert!(first.iter().zip(&second).all(|(a, b)| Rc::ptr_eq(a, b)));
+    }
+
+    #[test]
+    fn resolve_first_boxed_stops_at_the_first_match() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(0, |_| Box::new(Named("auth")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(1, |_| Box::new(Named("logging")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(2, |_| Box::new(Named("metrics")));
+
+        let provider = collection.build();
+        let found = provider.resolve_first_boxed::<dyn Middleware>(|m| m.name().starts_with('l'));
+
+        assert_eq!(found.map(|m| m.name()), Some("logging"));
+        assert_eq!(provider.cached_instances(), 2);
+        assert!(
+            provider
+                .resolve_first_boxed::<dyn Middleware>(|m| m.name() == "tracing")
+                .is_none()
+        );
+    }
+
+    struct Ingress;
+    struct Egress;
+
+    #[test]
+    fn sets_only_contain_their_own_members() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .register_in_set_boxed::<Ingress, dyn Middleware, _>(|_| Box::new(Named("auth")))
+            .register_in_set_boxed::<Egress, dyn Middleware, _>(|_| Box::new(Named("gzip")))
+            .register_in_set_boxed::<Ingress, dyn Middleware, _>(|_| Box::new(Named("limits")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(0, |_| Box::new(Named("logging")));
+
+        let provider = collection.build();
+        let ingress = provider.get_set_boxed::<Ingress, dyn Middleware>().unwrap();
+
+        let names = ingress.iter().map(|m| m.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["auth", "limits"]);
+        assert_eq!(provider.get_all_boxed::<dyn Middleware>().unwrap().len(), 1);
+        assert!(
+            provider
+                .get_set_boxed::<(), dyn Middleware>()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn add_collection_boxed_injects_every_implementation() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(1, |_| Box::new(Named("logging")))
+            .add_singleton_ordered_boxed::<dyn Middleware, _>(0, |_| Box::new(Named("auth")))
+            .add_collection_boxed::<dyn Middleware>();
+
+        let provider = collection.build();
+        let pipeline = provider.get::<Vec<Rc<Box<dyn Middleware>>>>().unwrap();
+
+        let names = pipeline.iter().map(|m| m.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["auth", "logging"]);
+        assert!(Rc::ptr_eq(
+            &pipeline,
+            &provider.get::<Vec<Rc<Box<dyn Middleware>>>>().unwrap()
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::unwind::OnUnwind;
+use crate::{Error, ServiceProvider};
+
+/// Instances that replace registered services for a single resolution.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub(crate) services: HashMap<TypeId, Rc<dyn Any>>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_boxed<T: ?Sized + 'static>(&mut self, instance: Box<T>) -> &mut Self {
+        self.add::<Box<T>>(instance)
+    }
+
+    pub fn add<T: Any + 'static>(&mut self, instance: T) -> &mut Self {
+        self.services.insert(TypeId::of::<T>(), Rc::new(instance));
+        self
+    }
+}
+
+impl ServiceProvider {
+    pub(crate) fn get_override(&self, type_id: &TypeId) -> Option<Rc<dyn Any>> {
+        self.overrides
+            .borrow()
+            .as_ref()?
+            .services
+            .get(type_id)
+            .cloned()
+    }
+
+    /// Resolves `T` while every `get` made by the factories involved, including
+    /// the one for `T` itself, prefers the instances in `overrides`.
+    ///
+    /// Services created during this resolution are not cached in the provider,
+    /// so they can't leak the overridden dependencies into later resolutions.
+    pub fn get_with_overrides_boxed<T: ?Sized + Any + 'static>(
+        &self,
+        overrides: Overrides,
+    ) -> Result<Rc<Box<T>>, Error> {
+        let mut cached = Some(self.replace_root_caches(self.root_caches()));
+        let mut previous = Some(self.overrides.replace(Some(overrides)));
+        let mut restore = || {
+            *self.overrides.borrow_mut() = previous.take().flatten();
+            self.replace_root_caches(cached.take().unwrap_or_default());
+        };
+
+        let guard = OnUnwind(&mut restore);
+        let service = self.get_boxed::<T>();
+        guard.disarm();
+        restore();
+
+        service
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::Overrides;
+    use crate::ServiceCollection;
+
+    trait Clock {
+        fn now(&self) -> u64;
+    }
+
+    struct SystemClock;
+    impl Clock for SystemClock {
+        fn now(&self) -> u64 {
+            1
+        }
+    }
+
+    struct FakeClock;
+    impl Clock for FakeClock {
+        fn now(&self) -> u64 {
+            42
+        }
+    }
+
+    trait Report {
+        fn timestamp(&self) -> u64;
+    }
+
+    struct ReportImpl(Rc<Box<dyn Clock>>);
+    impl Report for ReportImpl {
+        fn timestamp(&self) -> u64 {
+            self.0.now()
+        }
+    }
+
+    #[test]
+    fn overrides_apply_to_a_single_resolution() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Clock, _>(|_| Box::new(SystemClock));
+        collection.add_singleton_boxed::<dyn Report, _>(|provider| {
+            Box::new(ReportImpl(provider.get_boxed::<dyn Clock>().unwrap()))
+        });
+
+        let provider = collection.build();
+
+        let mut overrides = Overrides::new();
+        overrides.add_boxed::<dyn Clock>(Box::new(FakeClock));
+
+        let overridden = provider
+            .get_with_overrides_boxed::<dyn Report>(overrides)
+            .unwrap();
+        assert_eq!(overridden.timestamp(), 42);
+
+        let report = provider.get_boxed::<dyn Report>().unwrap();
+        assert_eq!(report.timestamp(), 1);
+        assert!(!Rc::ptr_eq(&overridden, &report));
+    }
+
+    #[test]
+    fn overridden_resolutions_leave_enumerable_singletons_uncached() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<dyn Clock, _>(|_| Box::new(SystemClock))
+            .add_singleton_ordered_boxed::<dyn Report, _>(0, |provider| {
+                Box::new(ReportImpl(provider.get_boxed::<dyn Clock>().unwrap()))
+            })
+            .add_transient_boxed::<u64, _>(|provider| {
+                Box::new(provider.get_all_boxed::<dyn Report>().unwrap()[0].timestamp())
+            });
+
+        let provider = collection.build();
+
+        let mut overrides = Overrides::new();
+        overrides.add_boxed::<dyn Clock>(Box::new(FakeClock));
+
+        let overridden = provider.get_with_overrides_boxed::<u64>(overrides).unwrap();
+        assert_eq!(**overridden, 42);
+        assert_eq!(provider.cached_instances(), 0);
+        assert!(provider.creation_order.borrow().is_empty());
+
+        assert_eq!(**provider.get_boxed::<u64>().unwrap(), 1);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{ServiceCollection, ServiceProvider};
+
+/// Which implementations [`ServiceCollection::register_platform_boxed`]
+/// picks, set with [`ServiceCollection::set_environment`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Environment {
+    #[default]
+    Production,
+    Test,
+}
+
+impl ServiceCollection {
+    /// Sets the environment the `register_platform_boxed` registrations are
+    /// resolved for, e.g. from `cfg!(test)` in the application's own crate
+    /// (the library's `cfg!(test)` says nothing about the caller's build).
+    pub fn set_environment(&mut self, environment: Environment) -> &mut Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Registers a singleton `T` built by `real` in production and by `test`
+    /// in tests, so the choice lives in one registration instead of `#[cfg]`s
+    /// scattered over the setup code.
+    ///
+    /// The choice is made on resolution, so `set_environment` may be called
+    /// before or after this.
+    pub fn register_platform_boxed<T: ?Sized + 'static>(
+        &mut self,
+        real: impl Fn(&ServiceProvider) -> Box<T> + 'static,
+        test: impl Fn(&ServiceProvider) -> Box<T> + 'static,
+    ) -> &mut Self {
+        self.add_singleton_boxed::<T, _>(move |provider| match provider.collection.environment {
+            Environment::Production => real(provider),
+            Environment::Test => test(provider),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Environment;
+    use crate::ServiceCollection;
+
+    trait FileSystem {
+        fn kind(&self) -> &'static str;
+    }
+
+    struct DiskFileSystem;
+    impl FileSystem for DiskFileSystem {
+        fn kind(&self) -> &'static str {
+            "disk"
+        }
+    }
+
+    struct MemoryFileSystem;
+    impl FileSystem for MemoryFileSystem {
+        fn kind(&self) -> &'static str {
+            "memory"
+        }
+    }
+
+    fn collection() -> ServiceCollection {
+        let mut collection = ServiceCollection::new();
+        collection.register_platform_boxed::<dyn FileSystem>(
+            |_| Box::new(DiskFileSystem),
+            |_| Box::new(MemoryFileSystem),
+        );
+        collection
+    }
+
+    #[test]
+    fn platform_registrations_follow_the_environment() {
+        let production = collection().build();
+        assert_eq!(
+            production.get_boxed::<dyn FileSystem>().unwrap().kind(),
+            "disk"
+        );
+
+        let mut test = collection();
+        test.set_environment(Environment::Test);
+        assert_eq!(
+            test.build().get_boxed::<dyn FileSystem>().unwrap().kind(),
+            "memory"
+        );
+    }
+}
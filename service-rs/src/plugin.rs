@@ -0,0 +1,79 @@
+use crate::ServiceCollection;
+
+/// Registration contract for services defined in other crates.
+///
+/// A plugin crate exports its registrations either as a type implementing
+/// `Plugin` or as a plain `pub fn register(c: &mut ServiceCollection)`, which
+/// qualifies through the blanket impl, and the host applies them all with
+/// [`ServiceCollection::apply_plugins`].
+pub trait Plugin {
+    fn register(&self, collection: &mut ServiceCollection);
+}
+
+impl<F: Fn(&mut ServiceCollection)> Plugin for F {
+    fn register(&self, collection: &mut ServiceCollection) {
+        self(collection)
+    }
+}
+
+impl ServiceCollection {
+    /// Lets every plugin register its services, in slice order, so later
+    /// plugins replace the single registrations of earlier ones.
+    pub fn apply_plugins(&mut self, plugins: &[&dyn Plugin]) -> &mut Self {
+        for plugin in plugins {
+            plugin.register(self);
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Plugin;
+    use crate::ServiceCollection;
+
+    trait Storage {
+        fn name(&self) -> &'static str;
+    }
+
+    struct Disk;
+    impl Storage for Disk {
+        fn name(&self) -> &'static str {
+            "disk"
+        }
+    }
+
+    struct Memory;
+    impl Storage for Memory {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+    }
+
+    struct MemoryPlugin;
+    impl Plugin for MemoryPlugin {
+        fn register(&self, collection: &mut ServiceCollection) {
+            collection.add_singleton_boxed::<dyn Storage, _>(|_| Box::new(Memory));
+        }
+    }
+
+    fn register_disk(collection: &mut ServiceCollection) {
+        collection
+            .add_singleton_boxed::<dyn Storage, _>(|_| Box::new(Disk))
+            .add_transient_boxed::<u32, _>(|_| Box::new(7));
+    }
+
+    #[test]
+    fn plugins_register_in_order_and_functions_qualify() {
+        let mut collection = ServiceCollection::new();
+        collection.apply_plugins(&[&register_disk, &MemoryPlugin]);
+
+        let provider = collection.build();
+        assert_eq!(
+            provider.get_boxed::<dyn Storage>().unwrap().name(),
+            "memory"
+        );
+        assert_eq!(**provider.get_boxed::<u32>().unwrap(), 7);
+    }
+}
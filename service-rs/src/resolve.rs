@@ -0,0 +1,49 @@
+use std::{any::Any, rc::Rc};
+
+use crate::{Error, ScopedServiceProvider, ServiceProvider};
+
+/// Types that can be resolved from a provider with the target type inferred
+/// from the binding:
+///
+/// ```
+/// # use std::rc::Rc;
+/// # use service_rs::{Error, ServiceCollection};
+/// # trait FirstDep {}
+/// # struct FirstDepImpl;
+/// # impl FirstDep for FirstDepImpl {}
+/// # fn main() -> Result<(), Error> {
+/// let mut collection = ServiceCollection::new();
+/// collection.add_singleton_boxed::<dyn FirstDep, _>(|_| Box::new(FirstDepImpl));
+/// let provider = collection.build();
+///
+/// let first: Rc<Box<dyn FirstDep>> = provider.resolve()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Resolve: Sized {
+    fn resolve_from(provider: &ServiceProvider) -> Result<Self, Error>;
+
+    fn resolve_from_scope(scope: &ScopedServiceProvider) -> Result<Self, Error>;
+}
+
+impl<T: Any + 'static> Resolve for Rc<T> {
+    fn resolve_from(provider: &ServiceProvider) -> Result<Self, Error> {
+        provider.get::<T>()
+    }
+
+    fn resolve_from_scope(scope: &ScopedServiceProvider) -> Result<Self, Error> {
+        scope.get::<T>()
+    }
+}
+
+impl ServiceProvider {
+    pub fn resolve<R: Resolve>(&self) -> Result<R, Error> {
+        R::resolve_from(self)
+    }
+}
+
+impl ScopedServiceProvider {
+    pub fn resolve<R: Resolve>(&self) -> Result<R, Error> {
+        R::resolve_from_scope(self)
+    }
+}
@@ -0,0 +1,695 @@
+use std::{
+    any::{Any, TypeId},
+    cell::{Cell, RefCell},
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    rc::Rc,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, RootEntry, ServiceCollection, ServiceProvider, downcast_service};
+
+type ServiceFuture = Pin<Box<dyn Future<Output = Result<Box<dyn Any>, Error>>>>;
+
+type AsyncFactoryFn = Rc<dyn Fn(&ServiceProvider) -> ServiceFuture>;
+
+// a singleton whose construction has to await, resolved through `get_async_boxed`
+#[derive(Clone)]
+pub(crate) struct AsyncFactory {
+    pub(crate) type_name: &'static str,
+    pub(crate) factory: AsyncFactoryFn,
+    // concurrent resolutions await one construction instead of starting their own
+    pub(crate) shared: bool,
+}
+
+impl std::fmt::Debug for AsyncFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncFactory")
+            .field("type_name", &self.type_name)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+// a construction awaited by every resolution that asked for the service while
+// it was pending, whichever of them gets polled drives the factory future
+pub(crate) struct InFlight {
+    future: RefCell<Option<ServiceFuture>>,
+    output: RefCell<Option<Result<Rc<dyn Any>, Error>>>,
+    waiters: Arc<Waiters>,
+    // resolutions currently awaiting the construction
+    waiting: Cell<usize>,
+}
+
+// one resolution awaiting an `InFlight`. once the last of them gives up,
+// timed out or dropped before the construction finished, the construction is
+// abandoned and its factory future dropped
+struct Waiting<'a> {
+    provider: &'a ServiceProvider,
+    type_id: TypeId,
+    in_flight: Rc<InFlight>,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(provider: &'a ServiceProvider, type_id: TypeId, in_flight: Rc<InFlight>) -> Self {
+        in_flight.waiting.set(in_flight.waiting.get() + 1);
+        Waiting {
+            provider,
+            type_id,
+            in_flight,
+        }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let in_flight = &self.in_flight;
+        in_flight.waiting.set(in_flight.waiting.get() - 1);
+
+        if in_flight.waiting.get() > 0 || in_flight.output.borrow().is_some() {
+            return;
+        }
+
+        let mut pending = self.provider.in_flight.borrow_mut();
+        if pending
+            .get(&self.type_id)
+            .is_some_and(|current| Rc::ptr_eq(current, in_flight))
+        {
+            pending.remove(&self.type_id);
+        }
+        drop(pending);
+
+        // dropped here rather than with the last `Rc`, which a factory awaiting
+        // itself may still hold
+        if let Ok(mut future) = in_flight.future.try_borrow_mut() {
+            future.take();
+        }
+    }
+}
+
+// the waker the factory future is polled with, wakes every waiting resolution
+// so the construction moves on even if the one that polled it last is gone
+#[derive(Default)]
+struct Waiters(Mutex<Vec<Waker>>);
+
+impl Waiters {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Wake for Waiters {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl std::fmt::Debug for InFlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InFlight")
+            .field("finished", &self.output.borrow().is_some())
+            .finish()
+    }
+}
+
+impl InFlight {
+    fn poll(&self, context: &mut Context<'_>) -> Poll<Result<Rc<dyn Any>, Error>> {
+        if let Some(output) = self.output.borrow().as_ref() {
+            return Poll::Ready(output.clone());
+        }
+
+        self.waiters.register(context.waker());
+        let waker = Waker::from(self.waiters.clone());
+
+        // already being polled further up, e.g. by a factory awaiting itself
+        if let Ok(mut future) = self.future.try_borrow_mut()
+            && let Some(pending) = future.as_mut()
+            && let Poll::Ready(output) = pending.as_mut().poll(&mut Context::from_waker(&waker))
+        {
+            let output = output.map(Rc::<dyn Any>::from);
+            *future = None;
+            *self.output.borrow_mut() = Some(output.clone());
+            waker.wake();
+
+            return Poll::Ready(output);
+        }
+
+        Poll::Pending
+    }
+}
+
+impl ServiceCollection {
+    /// Registers a singleton built by an async factory, e.g. one that has to
+    /// connect somewhere first. The factory resolves what it needs from the
+    /// provider up front and returns a future that doesn't borrow it.
+    ///
+    /// These services are resolved through `get_async_boxed::<T>()` only.
+    pub fn add_singleton_async_boxed<T: ?Sized + 'static, F, Fut>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Fut + 'static,
+        Fut: Future<Output = Result<Box<T>, Error>> + 'static,
+    {
+        self.register_async_boxed(false, factory)
+    }
+
+    /// Same as [`ServiceCollection::add_singleton_async_boxed`], except that
+    /// resolutions made while the factory future is pending all await that one
+    /// future instead of each running the factory, so an expensive singleton
+    /// is built once no matter how many requests ask for it at startup.
+    ///
+    /// If the factory fails, every waiting resolution gets the error and the
+    /// next one starts over. A resolution giving up on a timeout, or dropped,
+    /// leaves the construction to the others; when the last one gives up, the
+    /// factory future is dropped and the next resolution starts over.
+    pub fn add_singleton_shared_future_boxed<T: ?Sized + 'static, F, Fut>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Fut + 'static,
+        Fut: Future<Output = Result<Box<T>, Error>> + 'static,
+    {
+        self.register_async_boxed(true, factory)
+    }
+
+    fn register_async_boxed<T: ?Sized + 'static, F, Fut>(
+        &mut self,
+        shared: bool,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&ServiceProvider) -> Fut + 'static,
+        Fut: Future<Output = Result<Box<T>, Error>> + 'static,
+    {
+        self.async_factories.insert(
+            TypeId::of::<Box<T>>(),
+            AsyncFactory {
+                type_name: std::any::type_name::<Box<T>>(),
+                factory: Rc::new(move |provider| {
+                    let future = factory(provider);
+                    Box::pin(async move { Ok(Box::new(future.await?) as Box<dyn Any>) })
+                }),
+                shared,
+            },
+        );
+
+        self
+    }
+}
+
+impl ServiceProvider {
+    pub async fn get_async_boxed<T: ?Sized + Any + 'static>(&self) -> Result<Rc<Box<T>>, Error> {
+        self.resolve_async::<T>(None).await
+    }
+
+    /// Same as `get_async_boxed`, but gives up with [`Error::ResolveTimeout`] if
+    /// the factory hasn't finished after `dur`. The unfinished factory future is
+    /// dropped and nothing is cached; a construction shared through
+    /// [`ServiceCollection::add_singleton_shared_future_boxed`] is only dropped
+    /// once every resolution awaiting it has given up.
+    pub async fn get_async_boxed_timeout<T: ?Sized + Any + 'static>(
+        &self,
+        dur: Duration,
+    ) -> Result<Rc<Box<T>>, Error> {
+        self.resolve_async::<T>(Some(dur)).await
+    }
+
+    async fn resolve_async<T: ?Sized + Any + 'static>(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Rc<Box<T>>, Error> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+
+        let Some(descriptor) = self.collection.async_factories.get(&type_id) else {
+            return Err(Error::ServiceNotFound(type_name.to_string()));
+        };
+
+        let cached = self.services.borrow().get(&type_id).cloned();
+        if let Some(service) = cached {
+            self.record_hit(type_id);
+            return downcast_service(service, type_name);
+        }
+
+        self.ensure_cacheable(type_name)?;
+
+        let instance = if descriptor.shared {
+            self.await_in_flight(type_id, descriptor, timeout).await?
+        } else {
+            self.record_miss(type_id);
+
+            let future = (descriptor.factory)(self);
+            let instance = match timeout {
+                Some(dur) => with_timeout(future, dur)
+                    .await
+                    .ok_or_else(|| Error::ResolveTimeout(type_name.to_string()))??,
+                None => future.await?,
+            };
+            Rc::from(instance)
+        };
+
+        // another resolution may have finished first while this one was pending
+        let service = self
+            .services
+            .borrow_mut()
+            .entry(type_id)
+            .or_insert_with(|| {
+                self.creation_order
+                    .borrow_mut()
+                    .push(RootEntry::Service(type_id));
+                instance
+            })
+            .clone();
+
+        downcast_service(service, type_name)
+    }
+
+    // joins the construction of `type_id` in progress, or starts it
+    async fn await_in_flight(
+        &self,
+        type_id: TypeId,
+        descriptor: &AsyncFactory,
+        timeout: Option<Duration>,
+    ) -> Result<Rc<dyn Any>, Error> {
+        let joined = self.in_flight.borrow().get(&type_id).cloned();
+        let in_flight = match joined {
+            Some(in_flight) => {
+                self.record_hit(type_id);
+                in_flight
+            }
+            None => {
+                self.record_miss(type_id);
+                let in_flight = Rc::new(InFlight {
+                    future: RefCell::new(Some((descriptor.factory)(self))),
+                    output: RefCell::new(None),
+                    waiters: Arc::default(),
+                    waiting: Cell::new(0),
+                });
+                self.in_flight
+                    .borrow_mut()
+                    .insert(type_id, in_flight.clone());
+                in_flight
+            }
+        };
+
+        let _waiting = Waiting::new(self, type_id, in_flight.clone());
+        let waiting = poll_fn(|context| in_flight.poll(context));
+        let output = match timeout {
+            Some(dur) => with_timeout(waiting, dur)
+                .await
+                .ok_or_else(|| Error::ResolveTimeout(descriptor.type_name.to_string()))?,
+            None => waiting.await,
+        };
+
+        // the first waiter to see the output retires it, later resolutions hit
+        // the cache or, after a failure, start over
+        let mut pending = self.in_flight.borrow_mut();
+        if pending
+            .get(&type_id)
+            .is_some_and(|current| Rc::ptr_eq(current, &in_flight))
+        {
+            pending.remove(&type_id);
+        }
+
+        output
+    }
+}
+
+// polls `future` until it completes or `dur` elapses, whichever comes first
+async fn with_timeout<F: Future>(future: F, dur: Duration) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut delay = Delay::new(dur);
+
+    poll_fn(|context| {
+        if let Poll::Ready(output) = future.as_mut().poll(context) {
+            return Poll::Ready(Some(output));
+        }
+
+        delay.poll(context.waker()).map(|()| None)
+    })
+    .await
+}
+
+// a runtime-agnostic timer, the waiting happens on a helper thread. dropping
+// the delay cancels it, so the thread doesn't outlive a resolution that
+// finished early
+struct Delay {
+    // `None` for a duration too long to represent, which never elapses
+    deadline: Option<Instant>,
+    timer: Option<(Arc<Timer>, Thread)>,
+}
+
+struct Timer {
+    waker: Mutex<Waker>,
+    cancelled: AtomicBool,
+}
+
+impl Delay {
+    fn new(dur: Duration) -> Self {
+        Delay {
+            deadline: Instant::now().checked_add(dur),
+            timer: None,
+        }
+    }
+
+    fn poll(&mut self, waker: &Waker) -> Poll<()> {
+        let Some(deadline) = self.deadline else {
+            return Poll::Pending;
+        };
+        if Instant::now() >= deadline {
+            return Poll::Ready(());
+        }
+
+        match &self.timer {
+            Some((timer, _)) => timer
+                .waker
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone_from(waker),
+            None => {
+                let timer = Arc::new(Timer {
+                    waker: Mutex::new(waker.clone()),
+                    cancelled: AtomicBool::new(false),
+                });
+                let shared = timer.clone();
+
+                let handle = std::thread::spawn(move || {
+                    while !shared.cancelled.load(Ordering::Acquire) {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        if remaining.is_zero() {
+                            let waker = shared.waker.lock().unwrap_or_else(PoisonError::into_inner);
+                            waker.wake_by_ref();
+                            return;
+                        }
+                        std::thread::park_timeout(remaining);
+                    }
+                });
+
+                self.timer = Some((timer, handle.thread().clone()));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some((timer, thread)) = &self.timer {
+            timer.cancelled.store(true, Ordering::Release);
+            thread.unpark();
+        }
+    }
+}
+
+// drives `future` on the current thread, parking it until the future is woken.
+// shared with the tests of the other async modules
+#[cfg(test)]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        future::{Future, poll_fn},
+        pin::pin,
+        rc::Rc,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        task::{Context, Poll, Wake, Waker},
+        time::Duration,
+    };
+
+    use super::{Delay, block_on};
+    use crate::{Error, ServiceCollection};
+
+    #[test]
+    fn timed_out_factories_are_dropped_without_caching() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_async_boxed::<u32, _, _>(|_| async { Ok(Box::new(1)) })
+            .add_singleton_async_boxed::<u64, _, _>(|_| std::future::pending());
+
+        let provider = collection.build();
+
+        assert!(matches!(
+            block_on(provider.get_async_boxed_timeout::<u64>(Duration::from_millis(10))),
+            Err(Error::ResolveTimeout(_))
+        ));
+        assert_eq!(provider.cached_instances(), 0);
+
+        let first = block_on(provider.get_async_boxed_timeout::<u32>(Duration::from_secs(1)));
+        assert_eq!(**first.unwrap(), 1);
+        assert_eq!(provider.cached_instances(), 1);
+    }
+
+    // pending on the first poll, so a second resolution can join in meanwhile
+    async fn yield_once() {
+        let mut yielded = false;
+        poll_fn(|_| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn concurrent_resolutions_share_one_construction() {
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_shared_future_boxed::<u32, _, _>(move |_| {
+            let counter = counter.clone();
+            async move {
+                yield_once().await;
+                counter.set(counter.get() + 1);
+                Ok(Box::new(counter.get()))
+            }
+        });
+
+        let provider = collection.build();
+        let mut first = pin!(provider.get_async_boxed::<u32>());
+        let mut second = pin!(provider.get_async_boxed::<u32>());
+        let mut context = Context::from_waker(Waker::noop());
+        let (mut a, mut b) = (None, None);
+
+        // both are polled before either finishes
+        while a.is_none() || b.is_none() {
+            if a.is_none()
+                && let Poll::Ready(service) = first.as_mut().poll(&mut context)
+            {
+                a = Some(service);
+            }
+            if b.is_none()
+                && let Poll::Ready(service) = second.as_mut().poll(&mut context)
+            {
+                b = Some(service);
+            }
+        }
+
+        assert_eq!(built.get(), 1);
+        assert!(Rc::ptr_eq(&a.unwrap().unwrap(), &b.unwrap().unwrap()));
+        assert_eq!(provider.cached_instances(), 1);
+    }
+
+    // pending until opened, wakes whoever polled it last
+    #[derive(Default)]
+    struct Gate {
+        open: Cell<bool>,
+        waker: RefCell<Option<Waker>>,
+    }
+
+    impl Gate {
+        fn open(&self) {
+            self.open.set(true);
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+
+        async fn wait(&self) {
+            poll_fn(|context| {
+                if self.open.get() {
+                    Poll::Ready(())
+                } else {
+                    *self.waker.borrow_mut() = Some(context.waker().clone());
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn remaining_waiters_are_woken_after_the_driving_one_is_dropped() {
+        let gate = Rc::new(Gate::default());
+        let factory_gate = gate.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_shared_future_boxed::<u32, _, _>(move |_| {
+            let gate = factory_gate.clone();
+            async move {
+                gate.wait().await;
+                Ok(Box::new(1))
+            }
+        });
+
+        let provider = collection.build();
+        let mut first = Box::pin(provider.get_async_boxed::<u32>());
+        let mut second = pin!(provider.get_async_boxed::<u32>());
+
+        let flag = Arc::new(Flag::default());
+        let waker = Waker::from(flag.clone());
+        let mut context = Context::from_waker(&waker);
+
+        assert!(second.as_mut().poll(&mut context).is_pending());
+        // the first waiter polls the factory future last, then goes away
+        assert!(
+            first
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_pending()
+        );
+        drop(first);
+
+        gate.open();
+
+        assert!(flag.0.load(Ordering::SeqCst));
+        let Poll::Ready(service) = second.as_mut().poll(&mut context) else {
+            panic!("expected the construction to finish");
+        };
+        assert_eq!(**service.unwrap(), 1);
+    }
+
+    #[test]
+    fn dropped_delays_stop_their_timer_thread() {
+        let mut delay = Delay::new(Duration::from_secs(3600));
+        assert!(delay.poll(Waker::noop()).is_pending());
+
+        let timer = delay.timer.as_ref().unwrap().0.clone();
+        drop(delay);
+
+        for _ in 0..1000 {
+            if Arc::strong_count(&timer) == 1 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("timer thread still sleeping");
+    }
+
+    #[test]
+    fn unrepresentable_timeouts_never_elapse() {
+        let mut delay = Delay::new(Duration::MAX);
+
+        assert!(delay.poll(Waker::noop()).is_pending());
+        assert!(delay.timer.is_none());
+    }
+
+    // pending forever, records when it's dropped
+    struct Abandoned(Rc<Cell<bool>>);
+
+    impl Future for Abandoned {
+        type Output = Result<Box<u32>, Error>;
+
+        fn poll(self: std::pin::Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Abandoned {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn constructions_are_dropped_once_every_waiter_gave_up() {
+        let dropped = Rc::new(Cell::new(false));
+        let flag = dropped.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_shared_future_boxed::<u32, _, _>(move |_| Abandoned(flag.clone()));
+
+        let provider = collection.build();
+        let mut waiting = Box::pin(provider.get_async_boxed::<u32>());
+        assert!(
+            waiting
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_pending()
+        );
+
+        // another waiter remains, the construction goes on
+        assert!(matches!(
+            block_on(provider.get_async_boxed_timeout::<u32>(Duration::from_millis(10))),
+            Err(Error::ResolveTimeout(_))
+        ));
+        assert!(!dropped.get());
+
+        drop(waiting);
+        assert!(dropped.get());
+        assert!(provider.in_flight.borrow().is_empty());
+
+        dropped.set(false);
+        assert!(matches!(
+            block_on(provider.get_async_boxed_timeout::<u32>(Duration::from_millis(10))),
+            Err(Error::ResolveTimeout(_))
+        ));
+        assert!(dropped.get());
+        assert!(provider.in_flight.borrow().is_empty());
+    }
+}
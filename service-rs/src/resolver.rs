@@ -0,0 +1,138 @@
+use std::{
+    any::{Any, TypeId},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::{Error, Lifetime, ServiceDescriptor, ServiceProvider, downcast_service};
+
+/// Resolves one transient over and over without looking it up again, handed
+/// out by [`ServiceProvider::resolver_boxed`] for hot loops.
+///
+/// The descriptor is looked up once, when the resolver is created, so
+/// overrides and scope locals aren't consulted by [`Resolver::resolve`].
+/// Disabling the service on the provider and
+/// [`ServiceCollection::deny_transient_in_singleton`] still apply.
+///
+/// [`ServiceCollection::deny_transient_in_singleton`]: crate::ServiceCollection::deny_transient_in_singleton
+pub struct Resolver<'a, T: ?Sized> {
+    provider: &'a ServiceProvider,
+    descriptor: &'a ServiceDescriptor,
+    type_id: TypeId,
+    _service: PhantomData<fn() -> T>,
+}
+
+impl<T: ?Sized> std::fmt::Debug for Resolver<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Resolver")
+            .field(&self.descriptor.type_name)
+            .finish()
+    }
+}
+
+impl<T: Any + 'static> Resolver<'_, T> {
+    /// Builds a new instance through the captured factory.
+    pub fn resolve(&self) -> Result<Rc<T>, Error> {
+        let provider = self.provider;
+        let type_name = self.descriptor.type_name;
+
+        if provider.is_disabled(&self.type_id) {
+            return Err(Error::ServiceDisabled(type_name.to_string()));
+        }
+        if provider.collection.deny_transient_in_singleton {
+            provider.ensure_not_captured(type_name)?;
+        }
+
+        provider.record_dependency(type_name);
+        provider.record_miss(self.type_id);
+        let service = provider.create_from(self.descriptor, &self.type_id);
+        let injected = provider.run_injections();
+
+        let service = service?;
+        injected?;
+        downcast_service(service, type_name)
+    }
+}
+
+impl ServiceProvider {
+    /// Captures the factory of the transient `Box<T>` for repeated
+    /// resolution, see [`Resolver`]. Fails with [`Error::NotTransient`] for
+    /// anything whose instances get cached (singletons, scoped services,
+    /// transients shared within a resolution or cached in a scope).
+    pub fn resolver_boxed<T: ?Sized + 'static>(&self) -> Result<Resolver<'_, Box<T>>, Error> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+        let descriptor = self.descriptor(&type_id, type_name)?;
+
+        if descriptor.lifetime != Lifetime::Transient
+            || descriptor.shared_within_resolution
+            || descriptor.cache_in_scope
+        {
+            return Err(Error::NotTransient(type_name.to_string()));
+        }
+
+        Ok(Resolver {
+            provider: self,
+            descriptor,
+            type_id,
+            _service: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{Error, ServiceCollection};
+
+    #[test]
+    fn resolvers_build_a_fresh_transient_every_time() {
+        let built = Rc::new(Cell::new(0));
+        let counter = built.clone();
+
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_transient_boxed::<u32, _>(move |_| {
+                counter.set(counter.get() + 1);
+                Box::new(counter.get())
+            })
+            .add_singleton_boxed::<u64, _>(|_| Box::new(0));
+
+        let provider = collection.build();
+        let resolver = provider.resolver_boxed::<u32>().unwrap();
+
+        let values = (0..3)
+            .map(|_| **resolver.resolve().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![1, 2, 3]);
+
+        provider.set_enabled::<Box<u32>>(false);
+        assert!(matches!(resolver.resolve(), Err(Error::ServiceDisabled(_))));
+
+        assert!(matches!(
+            provider.resolver_boxed::<u64>(),
+            Err(Error::NotTransient(_))
+        ));
+    }
+
+    #[test]
+    fn resolvers_refuse_denied_transients_inside_singleton_factories() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .deny_transient_in_singleton()
+            .add_transient_boxed::<u16, _>(|_| Box::new(1))
+            .try_add_singleton_boxed::<u32, _>(|provider| {
+                let resolver = provider.resolver_boxed::<u16>()?;
+                Ok(Box::new(**resolver.resolve()? as u32))
+            });
+
+        let provider = collection.build();
+
+        assert!(provider.resolver_boxed::<u16>().unwrap().resolve().is_ok());
+        assert!(matches!(
+            provider.get_boxed::<u32>(),
+            Err(Error::TransientCapturedBySingleton { .. })
+        ));
+    }
+}
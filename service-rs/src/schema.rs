@@ -0,0 +1,129 @@
+use crate::{Lifetime, ServiceProvider};
+
+/// Description of one registered service, for documentation and diagrams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ServiceSchema {
+    pub type_name: &'static str,
+    pub lifetime: Lifetime,
+    /// Services the factory resolved while it ran, sorted by type name.
+    pub dependencies: Vec<&'static str>,
+}
+
+impl ServiceProvider {
+    /// Describes every registered service, sorted by type name. Dependencies
+    /// come from [`ServiceProvider::trace_graph`], so every factory runs once.
+    pub fn export_schema(&self) -> Vec<ServiceSchema> {
+        let graph = self.trace_graph();
+
+        let mut schema = self
+            .collection
+            .services
+            .values()
+            .map(|descriptor| ServiceSchema {
+                type_name: descriptor.type_name,
+                lifetime: descriptor.lifetime.clone(),
+                dependencies: graph
+                    .edges()
+                    .iter()
+                    .filter(|(dependent, _)| *dependent == descriptor.type_name)
+                    .map(|(_, dependency)| *dependency)
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        schema.sort_by_key(|service| service.type_name);
+        schema
+    }
+
+    /// Renders [`ServiceProvider::export_schema`] as a Graphviz DOT digraph,
+    /// e.g. for `dot -Tsvg`. Nodes are colored by lifetime (blue singleton,
+    /// green scoped, orange transient), edges point from a service to its
+    /// dependencies. Cycles are rendered as plain back-edges.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph services {\n");
+
+        for service in self.export_schema() {
+            let color = match service.lifetime {
+                Lifetime::Singleton => "blue",
+                Lifetime::Scoped => "green",
+                Lifetime::Transient => "orange",
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [color={}];\n",
+                escape(service.type_name),
+                color
+            ));
+
+            for dependency in service.dependencies {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    escape(service.type_name),
+                    escape(dependency)
+                ));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(type_name: &str) -> String {
+    type_name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Lifetime, ServiceCollection};
+
+    #[test]
+    fn export_schema_lists_lifetimes_and_dependencies() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_transient_boxed::<u64, _>(|provider| {
+            Box::new(**provider.get_boxed::<u32>().unwrap() as u64)
+        });
+
+        let schema = collection.build().export_schema();
+
+        assert_eq!(schema.len(), 2);
+        assert_eq!(schema[0].type_name, std::any::type_name::<Box<u32>>());
+        assert_eq!(schema[0].lifetime, Lifetime::Singleton);
+        assert!(schema[0].dependencies.is_empty());
+        assert_eq!(schema[1].lifetime, Lifetime::Transient);
+        assert_eq!(
+            schema[1].dependencies,
+            vec![std::any::type_name::<Box<u32>>()]
+        );
+    }
+
+    #[test]
+    fn to_dot_renders_cycles_as_back_edges() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|provider| {
+            Box::new(
+                provider
+                    .get_boxed::<u64>()
+                    .map_or(0, |value| **value as u32),
+            )
+        });
+        collection.add_transient_boxed::<u64, _>(|provider| {
+            Box::new(
+                provider
+                    .get_boxed::<u32>()
+                    .map_or(0, |value| **value as u64),
+            )
+        });
+
+        let dot = collection.build().to_dot();
+        let first = std::any::type_name::<Box<u32>>();
+        let second = std::any::type_name::<Box<u64>>();
+
+        assert!(dot.starts_with("digraph services {"));
+        assert!(dot.contains(&format!("\"{}\" [color=blue];", first)));
+        assert!(dot.contains(&format!("\"{}\" [color=orange];", second)));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\";", first, second)));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\";", second, first)));
+    }
+}
@@ -0,0 +1,92 @@
+use std::rc::Rc;
+
+use crate::{ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+#[derive(Clone)]
+pub(crate) struct ScopeHook(Rc<dyn Fn(u64)>);
+
+impl std::fmt::Debug for ScopeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ScopeHook")
+    }
+}
+
+impl ServiceCollection {
+    /// Adds a hook called with the id of every scope the provider creates,
+    /// e.g. to count live request scopes. Hooks run in the order they were
+    /// added.
+    pub fn on_scope_created(&mut self, hook: impl Fn(u64) + 'static) -> &mut Self {
+        self.scope_created_hooks.push(ScopeHook(Rc::new(hook)));
+        self
+    }
+
+    /// Adds a hook called with the id of every scope when it's dropped, after
+    /// the scope's own `on_dispose` callbacks ran.
+    pub fn on_scope_dropped(&mut self, hook: impl Fn(u64) + 'static) -> &mut Self {
+        self.scope_dropped_hooks.push(ScopeHook(Rc::new(hook)));
+        self
+    }
+}
+
+impl ServiceProvider {
+    // ids are unique per provider, starting at 1
+    pub(crate) fn scope_created(&self) -> u64 {
+        let id = self.next_scope_id.get() + 1;
+        self.next_scope_id.set(id);
+
+        for hook in &self.collection.scope_created_hooks {
+            hook.0(id);
+        }
+
+        id
+    }
+
+    pub(crate) fn scope_dropped(&self, id: u64) {
+        for hook in &self.collection.scope_dropped_hooks {
+            hook.0(id);
+        }
+    }
+}
+
+impl ScopedServiceProvider {
+    /// The id this scope was reported with to the scope hooks.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::ServiceCollection;
+
+    #[test]
+    fn hooks_see_every_scope_come_and_go() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+
+        let mut collection = ServiceCollection::new();
+        let log = events.clone();
+        collection.on_scope_created(move |id| log.borrow_mut().push(("created", id)));
+        let log = events.clone();
+        collection.on_scope_dropped(move |id| log.borrow_mut().push(("dropped", id)));
+
+        let provider = Rc::new(collection.build());
+        let first = provider.create_scope();
+        let second = first.create_inheriting_scope();
+        assert_eq!((first.id(), second.id()), (1, 2));
+
+        drop(first);
+        drop(second);
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                ("created", 1),
+                ("created", 2),
+                ("dropped", 1),
+                ("dropped", 2)
+            ]
+        );
+    }
+}
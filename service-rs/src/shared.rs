@@ -0,0 +1,135 @@
+use std::{any::Any, cell::RefCell, marker::PhantomData, rc::Rc};
+
+use crate::{Error, Lifetime, ScopedServiceProvider, ServiceCollection, ServiceProvider};
+
+/// Returned by [`ServiceCollection::add_singleton_shared`] to expose the shared
+/// concrete singleton under additional trait-object keys.
+pub struct SharedSingletonBuilder<'a, C> {
+    pub(crate) collection: &'a mut ServiceCollection,
+    pub(crate) _concrete: PhantomData<C>,
+}
+
+impl<C: Any + 'static> SharedSingletonBuilder<'_, C> {
+    /// Registers `Box<T>` as a singleton view over the shared `C`.
+    ///
+    /// A `Box<dyn Trait>` can't borrow from the cached instance, so `view`
+    /// receives its own `Rc<C>` handle and has to move it into the box, e.g. by
+    /// implementing the trait for `Rc<C>` or for a small wrapper around it. Every
+    /// view keeps the concrete instance alive for as long as the view itself.
+    #[doc(alias = "as_boxed")]
+    pub fn also_as_boxed<T: ?Sized + 'static>(
+        self,
+        view: impl Fn(Rc<C>) -> Box<T> + 'static,
+    ) -> Self {
+        self.collection
+            .try_add_singleton_boxed::<T, _>(move |provider| Ok(view(provider.get::<C>()?)));
+
+        self
+    }
+}
+
+impl ServiceCollection {
+    /// Registers `C` as a singleton that can be resolved through `get::<C>()`
+    /// and through every trait-object view added with
+    /// [`SharedSingletonBuilder::also_as_boxed`], all of them backed by the one
+    /// cached instance.
+    pub fn add_singleton_shared<C: Any + 'static>(
+        &mut self,
+        factory: impl Fn(&ServiceProvider) -> C + 'static,
+    ) -> SharedSingletonBuilder<'_, C> {
+        self.register(
+            std::any::TypeId::of::<C>(),
+            std::any::type_name::<C>(),
+            Lifetime::Singleton,
+            Rc::new(move |provider| Ok(Box::new(factory(provider)) as Box<dyn Any>)),
+        );
+
+        SharedSingletonBuilder {
+            collection: self,
+            _concrete: PhantomData,
+        }
+    }
+
+    /// Registers a singleton holding mutable state, every consumer resolving it
+    /// through `get_shared::<T>()` gets the same `Rc<RefCell<T>>`.
+    pub fn add_shared_singleton<T: Any + 'static>(
+        &mut self,
+        factory: impl Fn(&ServiceProvider) -> T + 'static,
+    ) -> &mut Self {
+        self.add_singleton::<RefCell<T>, _>(move |provider| {
+            Box::new(RefCell::new(factory(provider)))
+        })
+    }
+}
+
+impl ServiceProvider {
+    pub fn get_shared<T: Any + 'static>(&self) -> Result<Rc<RefCell<T>>, Error> {
+        self.get::<RefCell<T>>()
+    }
+}
+
+impl ScopedServiceProvider {
+    pub fn get_shared<T: Any + 'static>(&self) -> Result<Rc<RefCell<T>>, Error> {
+        self.get::<RefCell<T>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::ServiceCollection;
+
+    trait Reader {
+        fn read(&self) -> String;
+    }
+
+    trait Writer {
+        fn write(&self, value: &str);
+    }
+
+    #[derive(Default)]
+    struct Buffer(RefCell<String>);
+
+    impl Reader for Rc<Buffer> {
+        fn read(&self) -> String {
+            self.0.borrow().clone()
+        }
+    }
+
+    impl Writer for Rc<Buffer> {
+        fn write(&self, value: &str) {
+            self.0.borrow_mut().push_str(value);
+        }
+    }
+
+    #[test]
+    fn trait_views_share_the_concrete_singleton() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_shared(|_| Buffer::default())
+            .also_as_boxed::<dyn Reader>(|buffer| Box::new(buffer))
+            .also_as_boxed::<dyn Writer>(|buffer| Box::new(buffer));
+
+        let provider = collection.build();
+        provider.get_boxed::<dyn Writer>().unwrap().write("hello");
+
+        assert_eq!(provider.get_boxed::<dyn Reader>().unwrap().read(), "hello");
+        assert_eq!(*provider.get::<Buffer>().unwrap().0.borrow(), "hello");
+    }
+
+    #[test]
+    fn shared_singleton_mutations_are_visible_to_every_handle() {
+        let mut collection = ServiceCollection::new();
+        collection.add_shared_singleton(|_| Vec::<u32>::new());
+
+        let provider = Rc::new(collection.build());
+        let writer = provider.get_shared::<Vec<u32>>().unwrap();
+        let reader = provider.create_scope().get_shared::<Vec<u32>>().unwrap();
+
+        writer.borrow_mut().push(7);
+
+        assert!(Rc::ptr_eq(&writer, &reader));
+        assert_eq!(*reader.borrow(), vec![7]);
+    }
+}
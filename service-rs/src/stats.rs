@@ -0,0 +1,106 @@
+use std::any::{Any, TypeId};
+
+use crate::ServiceProvider;
+
+/// How often resolving a service was served from a cache (`hits`) versus by
+/// running its factory (`misses`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolveStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl ServiceProvider {
+    pub(crate) fn record_hit(&self, type_id: TypeId) {
+        self.stats.borrow_mut().entry(type_id).or_default().hits += 1;
+
+        #[cfg(feature = "log")]
+        self.log_resolution(type_id, "cache hit");
+    }
+
+    pub(crate) fn record_miss(&self, type_id: TypeId) {
+        self.stats.borrow_mut().entry(type_id).or_default().misses += 1;
+
+        #[cfg(feature = "log")]
+        self.log_resolution(type_id, "constructing");
+    }
+
+    /// Resolution statistics of `T` across this provider and all of its scopes.
+    pub fn stats<T: Any + 'static>(&self) -> ResolveStats {
+        self.stats
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Type names of the registered services that were never resolved through
+    /// this provider or its scopes, sorted. Candidates for removal.
+    pub fn unused_registrations(&self) -> Vec<&'static str> {
+        // every resolution of a registration counts a hit or a miss
+        let stats = self.stats.borrow();
+        let unused = self
+            .collection
+            .services
+            .keys()
+            .filter(|type_id| !stats.contains_key(type_id));
+
+        self.collection.type_names(unused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::ResolveStats;
+    use crate::ServiceCollection;
+
+    #[test]
+    fn stats_count_cache_hits_and_factory_runs() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_transient_boxed::<u64, _>(|_| Box::new(2));
+        collection.add_scoped_boxed::<u16, _>(|_| Box::new(3));
+
+        let provider = Rc::new(collection.build());
+        for _ in 0..3 {
+            provider.get_boxed::<u32>().unwrap();
+            provider.get_boxed::<u64>().unwrap();
+        }
+
+        let scope = provider.create_scope();
+        scope.get_boxed::<u16>().unwrap();
+        scope.get_boxed::<u16>().unwrap();
+
+        assert_eq!(
+            provider.stats::<Box<u32>>(),
+            ResolveStats { hits: 2, misses: 1 }
+        );
+        assert_eq!(
+            provider.stats::<Box<u64>>(),
+            ResolveStats { hits: 0, misses: 3 }
+        );
+        assert_eq!(
+            provider.stats::<Box<u16>>(),
+            ResolveStats { hits: 1, misses: 1 }
+        );
+    }
+
+    #[test]
+    fn unused_registrations_lists_services_never_resolved() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+        collection.add_transient_boxed::<u64, _>(|_| Box::new(2));
+        collection.add_scoped_boxed::<u16, _>(|_| Box::new(3));
+
+        let provider = Rc::new(collection.build());
+        provider.get_boxed::<u32>().unwrap();
+        provider.create_scope().get_boxed::<u16>().unwrap();
+
+        assert_eq!(
+            provider.unused_registrations(),
+            vec![std::any::type_name::<Box<u64>>()]
+        );
+    }
+}
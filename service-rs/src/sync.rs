@@ -0,0 +1,308 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{Error, Lifetime};
+
+type SyncService = Arc<dyn Any + Send + Sync>;
+
+type SyncServiceFactory =
+    Box<dyn Fn(&SyncServiceProvider) -> Result<Box<dyn Any + Send + Sync>, Error> + Send + Sync>;
+
+pub(crate) struct SyncServiceDescriptor {
+    pub(crate) lifetime: Lifetime,
+    pub(crate) factory: SyncServiceFactory,
+    pub(crate) type_name: &'static str,
+}
+
+impl std::fmt::Debug for SyncServiceDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncServiceDescriptor")
+            .field("lifetime", &self.lifetime)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+/// Thread-safe counterpart of [`ServiceCollection`](crate::ServiceCollection),
+/// every service and factory has to be `Send + Sync`.
+#[derive(Debug, Default)]
+pub struct SyncServiceCollection {
+    pub(crate) services: HashMap<TypeId, SyncServiceDescriptor>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SyncServiceState {
+    pub(crate) services: HashMap<TypeId, SyncServiceDescriptor>,
+    pub(crate) singletons: Mutex<HashMap<TypeId, SyncService>>,
+}
+
+/// Thread-safe counterpart of [`ServiceProvider`](crate::ServiceProvider).
+///
+/// Cloning is cheap, clones share the descriptors and the singleton cache, so
+/// worker threads each take a clone (or share one by reference). Scoped
+/// services resolved outside of a scope are built fresh like transients.
+#[derive(Debug, Clone)]
+pub struct SyncServiceProvider {
+    pub(crate) state: Arc<SyncServiceState>,
+    // the scope this provider resolves in, handed to the factories of scoped
+    // services so their scoped dependencies land in the same scope
+    pub(crate) scope: Option<Arc<Mutex<HashMap<TypeId, SyncService>>>>,
+}
+
+#[derive(Debug)]
+pub struct SyncScopedServiceProvider {
+    pub(crate) provider: SyncServiceProvider,
+}
+
+impl SyncServiceCollection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register_boxed<T: ?Sized + Send + Sync + 'static, F>(
+        &mut self,
+        lifetime: Lifetime,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&SyncServiceProvider) -> Box<T> + Send + Sync + 'static,
+    {
+        self.services.insert(
+            TypeId::of::<Box<T>>(),
+            SyncServiceDescriptor {
+                lifetime,
+                factory: Box::new(move |provider| {
+                    Ok(Box::new(factory(provider)) as Box<dyn Any + Send + Sync>)
+                }),
+                type_name: std::any::type_name::<Box<T>>(),
+            },
+        );
+
+        self
+    }
+
+    pub fn add_singleton_boxed<T: ?Sized + Send + Sync + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&SyncServiceProvider) -> Box<T> + Send + Sync + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Singleton, factory)
+    }
+
+    pub fn add_transient_boxed<T: ?Sized + Send + Sync + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&SyncServiceProvider) -> Box<T> + Send + Sync + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Transient, factory)
+    }
+
+    pub fn add_scoped_boxed<T: ?Sized + Send + Sync + 'static, F>(
+        &mut self,
+        factory: F,
+    ) -> &mut Self
+    where
+        F: Fn(&SyncServiceProvider) -> Box<T> + Send + Sync + 'static,
+    {
+        self.register_boxed::<T, _>(Lifetime::Scoped, factory)
+    }
+
+    pub fn build(self) -> SyncServiceProvider {
+        SyncServiceProvider {
+            state: Arc::new(SyncServiceState {
+                services: self.services,
+                singletons: Mutex::new(HashMap::new()),
+            }),
+            scope: None,
+        }
+    }
+}
+
+impl SyncServiceProvider {
+    pub fn create_scope(&self) -> SyncScopedServiceProvider {
+        SyncScopedServiceProvider {
+            provider: SyncServiceProvider {
+                state: self.state.clone(),
+                scope: Some(Arc::new(Mutex::new(HashMap::new()))),
+            },
+        }
+    }
+
+    pub(crate) fn create(&self, type_id: &TypeId, type_name: &str) -> Result<SyncService, Error> {
+        let descriptor = self
+            .state
+            .services
+            .get(type_id)
+            .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))?;
+
+        Ok(Arc::from(descriptor.factory.as_ref()(self)?))
+    }
+
+    pub(crate) fn lifetime(&self, type_id: &TypeId, type_name: &str) -> Result<Lifetime, Error> {
+        self.state
+            .services
+            .get(type_id)
+            .map(|descriptor| descriptor.lifetime.clone())
+            .ok_or_else(|| Error::ServiceNotFound(type_name.to_string()))
+    }
+
+    pub fn get_boxed<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<Box<T>>, Error> {
+        self.get::<Box<T>>()
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Result<Arc<T>, Error> {
+        let type_id = TypeId::of::<T>();
+        let type_name = std::any::type_name::<T>();
+
+        let service = match (self.lifetime(&type_id, type_name)?, &self.scope) {
+            // singletons are built by the root, they can't hold on to a scope
+            (Lifetime::Singleton, _) => {
+                let root = SyncServiceProvider {
+                    state: self.state.clone(),
+                    scope: None,
+                };
+                root.cached_or_create(&self.state.singletons, &type_id, type_name)?
+            }
+            (Lifetime::Scoped, Some(scope)) => self.cached_or_create(scope, &type_id, type_name)?,
+            (Lifetime::Scoped | Lifetime::Transient, _) => self.create(&type_id, type_name)?,
+        };
+
+        service
+            .downcast::<T>()
+            .map_err(|_| Error::FactoryTypeMismatch {
+                registered: type_name,
+            })
+    }
+
+    fn cached_or_create(
+        &self,
+        cache: &Mutex<HashMap<TypeId, SyncService>>,
+        type_id: &TypeId,
+        type_name: &str,
+    ) -> Result<SyncService, Error> {
+        if let Some(service) = lock(cache).get(type_id).cloned() {
+            return Ok(service);
+        }
+
+        // the lock isn't held while the factory runs so it can resolve other
+        // services of the same cache, if two threads race here the first
+        // instance to be cached wins.
+        let service = self.create(type_id, type_name)?;
+
+        Ok(lock(cache).entry(*type_id).or_insert(service).clone())
+    }
+}
+
+// a factory panicking elsewhere doesn't leave the map itself inconsistent
+fn lock(
+    cache: &Mutex<HashMap<TypeId, SyncService>>,
+) -> std::sync::MutexGuard<'_, HashMap<TypeId, SyncService>> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl SyncScopedServiceProvider {
+    pub fn get_boxed<T: ?Sized + Send + Sync + 'static>(&self) -> Result<Arc<Box<T>>, Error> {
+        self.get::<Box<T>>()
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Result<Arc<T>, Error> {
+        self.provider.get::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::SyncServiceCollection;
+
+    trait Counter: Send + Sync {}
+    struct CounterImpl;
+    impl Counter for CounterImpl {}
+
+    trait Request: Send + Sync {}
+    struct RequestImpl;
+    impl Request for RequestImpl {}
+
+    #[test]
+    fn clones_share_singletons_across_threads() {
+        let mut collection = SyncServiceCollection::new();
+        collection.add_singleton_boxed::<dyn Counter, _>(|_| Box::new(CounterImpl));
+        collection.add_scoped_boxed::<dyn Request, _>(|_| Box::new(RequestImpl));
+
+        let provider = collection.build();
+        let counter = provider.get_boxed::<dyn Counter>().unwrap();
+
+        let workers = (0..4)
+            .map(|_| {
+                let provider = provider.clone();
+                thread::spawn(move || {
+                    let scope = provider.create_scope();
+                    let request = scope.get_boxed::<dyn Request>().unwrap();
+                    assert!(Arc::ptr_eq(
+                        &request,
+                        &scope.get_boxed::<dyn Request>().unwrap()
+                    ));
+
+                    provider.get_boxed::<dyn Counter>().unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            assert!(Arc::ptr_eq(&counter, &worker.join().unwrap()));
+        }
+    }
+
+    #[test]
+    fn providers_and_scopes_are_shareable() {
+        fn shareable<T: Send + Sync>() {}
+
+        shareable::<super::SyncServiceProvider>();
+        shareable::<super::SyncScopedServiceProvider>();
+    }
+
+    struct Session(Arc<Box<dyn Request>>);
+
+    #[test]
+    fn scoped_dependencies_stay_in_the_resolving_scope() {
+        let mut collection = SyncServiceCollection::new();
+        collection
+            .add_scoped_boxed::<dyn Request, _>(|_| Box::new(RequestImpl))
+            .add_scoped_boxed::<Session, _>(|provider| {
+                Box::new(Session(provider.get_boxed::<dyn Request>().unwrap()))
+            });
+
+        let provider = collection.build();
+        let first = provider.create_scope();
+        let second = provider.create_scope();
+
+        let session = first.get_boxed::<Session>().unwrap();
+        assert!(Arc::ptr_eq(
+            &session.0,
+            &first.get_boxed::<dyn Request>().unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &session.0,
+            &second.get_boxed::<Session>().unwrap().0
+        ));
+
+        // outside of a scope nothing is cached
+        let root = provider.get_boxed::<Session>().unwrap();
+        assert!(!Arc::ptr_eq(
+            &root,
+            &provider.get_boxed::<Session>().unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &root.0,
+            &provider.get_boxed::<dyn Request>().unwrap()
+        ));
+    }
+}
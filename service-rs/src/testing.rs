@@ -0,0 +1,98 @@
+//! Assertions for checking that services are registered with the lifetime
+//! the tests expect. For trait objects, pass the boxed type, e.g.
+//! `assert_singleton::<Box<dyn Greeter>>(&provider)`.
+
+use std::{any::Any, rc::Rc};
+
+use crate::ServiceProvider;
+
+/// Resolves `T` twice and asserts both resolutions return the same instance.
+pub fn assert_singleton<T: Any + 'static>(provider: &ServiceProvider) {
+    let first = resolve::<T>(provider);
+    let second = resolve::<T>(provider);
+
+    assert!(
+        Rc::ptr_eq(&first, &second),
+        "expected {} to be a singleton, got two instances",
+        std::any::type_name::<T>()
+    );
+}
+
+/// Resolves `T` twice and asserts both resolutions return a new instance.
+pub fn assert_transient<T: Any + 'static>(provider: &ServiceProvider) {
+    let first = resolve::<T>(provider);
+    let second = resolve::<T>(provider);
+
+    assert!(
+        !Rc::ptr_eq(&first, &second),
+        "expected {} to be transient, got the same instance twice",
+        std::any::type_name::<T>()
+    );
+}
+
+/// Asserts `T` is cached per scope: resolving it twice in one scope gives the
+/// same instance, resolving it in another scope gives a different one.
+pub fn assert_scoped_differs_across_scopes<T: Any + 'static>(provider: &Rc<ServiceProvider>) {
+    let type_name = std::any::type_name::<T>();
+    let first = provider.create_scope();
+    let second = provider.create_scope();
+
+    let resolve_in = |scope: &crate::ScopedServiceProvider| {
+        scope
+            .get::<T>()
+            .unwrap_or_else(|error| panic!("failed to resolve {}: {}", type_name, error))
+    };
+
+    assert!(
+        Rc::ptr_eq(&resolve_in(&first), &resolve_in(&first)),
+        "expected {} to be cached within a scope",
+        type_name
+    );
+    assert!(
+        !Rc::ptr_eq(&resolve_in(&first), &resolve_in(&second)),
+        "expected {} to differ across scopes",
+        type_name
+    );
+}
+
+fn resolve<T: Any + 'static>(provider: &ServiceProvider) -> Rc<T> {
+    provider.get::<T>().unwrap_or_else(|error| {
+        panic!(
+            "failed to resolve {}: {}",
+            std::any::type_name::<T>(),
+            error
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{assert_scoped_differs_across_scopes, assert_singleton, assert_transient};
+    use crate::ServiceCollection;
+
+    #[test]
+    fn assertions_match_registered_lifetimes() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(1))
+            .add_transient_boxed::<u64, _>(|_| Box::new(2))
+            .add_scoped_boxed::<u16, _>(|_| Box::new(3));
+
+        let provider = Rc::new(collection.build());
+
+        assert_singleton::<Box<u32>>(&provider);
+        assert_transient::<Box<u64>>(&provider);
+        assert_scoped_differs_across_scopes::<Box<u16>>(&provider);
+    }
+
+    #[test]
+    #[should_panic(expected = "to be transient")]
+    fn assert_transient_rejects_singletons() {
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+
+        assert_transient::<Box<u32>>(&collection.build());
+    }
+}
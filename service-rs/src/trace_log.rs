@@ -0,0 +1,67 @@
+//! `log::trace!` entries for every resolution, behind the `log` feature.
+
+use std::any::TypeId;
+
+use crate::{Error, ServiceProvider};
+
+impl ServiceProvider {
+    pub(crate) fn log_resolution(&self, type_id: TypeId, outcome: &'static str) {
+        if let Some(descriptor) = self.collection.services.get(&type_id) {
+            log::trace!(
+                "{}: {} ({:?})",
+                outcome,
+                descriptor.type_name,
+                descriptor.lifetime
+            );
+        }
+    }
+}
+
+pub(crate) fn log_failure(type_name: &'static str, error: &Error) {
+    log::trace!("failed: {} ({})", type_name, error);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::ServiceCollection;
+
+    thread_local! {
+        static ENTRIES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // tests run in parallel, every thread only sees its own entries
+    struct Capture;
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            ENTRIES.with(|entries| entries.borrow_mut().push(record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn resolutions_are_traced() {
+        let _ = log::set_logger(&Capture);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let mut collection = ServiceCollection::new();
+        collection.add_singleton_boxed::<u32, _>(|_| Box::new(1));
+
+        let provider = collection.build();
+        provider.get_boxed::<u32>().unwrap();
+        provider.get_boxed::<u32>().unwrap();
+        provider.get_boxed::<u64>().unwrap_err();
+
+        let entries = ENTRIES.with(|entries| entries.take());
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].starts_with("constructing: ") && entries[0].ends_with("(Singleton)"));
+        assert!(entries[1].starts_with("cache hit: "));
+        assert!(entries[2].starts_with("failed: "));
+    }
+}
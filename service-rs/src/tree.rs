@@ -0,0 +1,133 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::unwind::OnUnwind;
+use crate::{Error, ServiceProvider, downcast_service};
+
+/// A service together with every dependency resolved while building it, see
+/// [`ServiceProvider::resolve_tree_boxed`].
+#[derive(Debug, Clone)]
+pub struct ResolvedTree {
+    root: &'static str,
+    services: HashMap<&'static str, Rc<dyn Any>>,
+}
+
+impl ResolvedTree {
+    /// Type name of the service the tree was resolved for.
+    pub fn root(&self) -> &'static str {
+        self.root
+    }
+
+    /// The instance of `T` in the tree, if `T` was resolved while building it.
+    pub fn get<T: Any>(&self) -> Option<Rc<T>> {
+        self.services
+            .get(std::any::type_name::<T>())?
+            .clone()
+            .downcast::<T>()
+            .ok()
+    }
+
+    pub fn get_boxed<T: ?Sized + Any>(&self) -> Option<Rc<Box<T>>> {
+        self.get::<Box<T>>()
+    }
+
+    /// Type names of every service in the tree, sorted.
+    pub fn type_names(&self) -> Vec<&'static str> {
+        let mut type_names = self.services.keys().copied().collect::<Vec<_>>();
+        type_names.sort_unstable();
+        type_names
+    }
+
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+}
+
+impl ServiceProvider {
+    /// Resolves `Box<T>` and collects every service resolved while building
+    /// it, transitively, by type name. Handy to inspect a fully wired
+    /// aggregate or hand a coherent object graph over somewhere else.
+    ///
+    /// Only what factories actually resolve is recorded: if `T` is already
+    /// cached, no factory runs and the tree holds `T` alone.
+    pub fn resolve_tree_boxed<T: ?Sized + Any + 'static>(&self) -> Result<ResolvedTree, Error> {
+        let type_id = TypeId::of::<Box<T>>();
+        let type_name = std::any::type_name::<Box<T>>();
+
+        let mut outer = Some(self.recorded.replace(Some(HashMap::new())));
+        let guard = OnUnwind(|| {
+            *self.recorded.borrow_mut() = outer.take().flatten();
+        });
+        let service = self.resolve_any(type_id, type_name);
+        guard.disarm();
+        let mut services = self.recorded.replace(outer.flatten()).unwrap_or_default();
+
+        let service = downcast_service::<Box<T>>(service?, type_name)?;
+        services.insert(type_name, service);
+
+        Ok(ResolvedTree {
+            root: type_name,
+            services,
+        })
+    }
+
+    // called as every resolution finishes, a no-op unless `resolve_tree_boxed`
+    // is recording
+    pub(crate) fn record_resolved(&self, type_name: &'static str, service: &Rc<dyn Any>) {
+        if let Some(recorded) = self.recorded.borrow_mut().as_mut() {
+            recorded.insert(type_name, service.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::ServiceCollection;
+
+    trait Repository {}
+    struct RepositoryImpl;
+    impl Repository for RepositoryImpl {}
+
+    trait Handler {}
+    #[allow(dead_code)]
+    struct HandlerImpl(Rc<Box<dyn Repository>>, u32);
+    impl Handler for HandlerImpl {}
+
+    #[test]
+    fn resolve_tree_boxed_collects_transitive_dependencies() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u32, _>(|_| Box::new(7))
+            .add_singleton_boxed::<dyn Repository, _>(|provider| {
+                provider.get_boxed::<u32>().unwrap();
+                Box::new(RepositoryImpl)
+            })
+            .add_transient_boxed::<dyn Handler, _>(|provider| {
+                Box::new(HandlerImpl(
+                    provider.get_boxed::<dyn Repository>().unwrap(),
+                    **provider.get_boxed::<u32>().unwrap(),
+                ))
+            })
+            .add_singleton_boxed::<u64, _>(|_| Box::new(0));
+
+        let provider = collection.build();
+        let tree = provider.resolve_tree_boxed::<dyn Handler>().unwrap();
+
+        assert_eq!(tree.len(), 3);
+        assert_eq!(**tree.get_boxed::<u32>().unwrap(), 7);
+        assert!(Rc::ptr_eq(
+            &tree.get_boxed::<dyn Repository>().unwrap(),
+            &provider.get_boxed::<dyn Repository>().unwrap()
+        ));
+        assert!(tree.get_boxed::<u64>().is_none());
+    }
+}
@@ -0,0 +1,26 @@
+use std::any::Any;
+
+/// Types that can be registered and resolved through the unboxed methods
+/// (`add_singleton`, `get`, ...), implemented for every sized `'static` type.
+///
+/// Trait objects and other unsized types have to go through the `_boxed`
+/// variants instead:
+///
+/// ```compile_fail
+/// # use service_rs::ServiceCollection;
+/// trait Greeter {}
+///
+/// let mut collection = ServiceCollection::new();
+/// collection.add_singleton::<dyn Greeter, _>(|_| Box::new(()));
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be registered or resolved unboxed",
+    label = "unsized types can't be stored by value",
+    note = "use the `_boxed` variant instead, e.g. `add_singleton_boxed::<{Self}, _>` or `get_boxed::<{Self}>`"
+)]
+//
+// the methods bound on it take `T: ?Sized + Unboxed`, so an unsized `T` fails on
+// this trait and its message instead of on the implicit `Sized` bound
+pub trait Unboxed: Sized + Any {}
+
+impl<T: Any> Unboxed for T {}
@@ -0,0 +1,100 @@
+// a factory panicking in the middle of a resolution unwinds through frames that
+// pushed onto the provider's stacks or left a sentinel in a cache. the guards
+// here undo that, so a caller catching the panic keeps a usable provider.
+
+// runs the closure if dropped while unwinding, `disarm` once the frame has
+// cleaned up after itself
+pub(crate) struct OnUnwind<F: FnMut()>(pub(crate) F);
+
+impl<F: FnMut()> OnUnwind<F> {
+    pub(crate) fn disarm(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl<F: FnMut()> Drop for OnUnwind<F> {
+    fn drop(&mut self) {
+        (self.0)();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+    use std::rc::Rc;
+
+    use crate::{Inject, Overrides, ServiceCollection, ServiceProvider};
+
+    #[test]
+    fn panicking_factories_leave_the_provider_usable() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u8, _>(|_| panic!("u8 factory"))
+            .add_scoped_boxed::<u16, _>(|provider| {
+                Box::new(**provider.get_boxed::<u8>().unwrap() as u16)
+            })
+            .add_scoped_boxed::<u32, _>(|_| Box::new(32))
+            .add_singleton_boxed::<u64, _>(|provider| Box::new(provider.current_depth() as u64));
+
+        let provider = Rc::new(collection.build());
+        let scope = provider.create_scope();
+        let scoped = scope.get_boxed::<u32>().unwrap();
+
+        let panicked = catch_unwind(AssertUnwindSafe(|| scope.get_boxed::<u16>()));
+        assert!(panicked.is_err());
+
+        // with the sentinel left behind this would be a `SingletonReentrancy`
+        // error, instead the factory runs (and panics) again
+        let panicked = catch_unwind(AssertUnwindSafe(|| provider.get_boxed::<u8>()));
+        assert!(panicked.is_err());
+
+        assert_eq!(**provider.get_boxed::<u64>().unwrap(), 0);
+        assert!(Rc::ptr_eq(&scoped, &scope.get_boxed::<u32>().unwrap()));
+        assert!(provider.current_chain().is_empty());
+        assert_eq!(provider.cached_instances(), 1);
+    }
+
+    struct Unbindable;
+    impl Inject for Unbindable {
+        fn inject(&self, _: &ServiceProvider) -> Result<(), crate::Error> {
+            panic!("inject")
+        }
+    }
+
+    #[test]
+    fn panics_while_tracing_overriding_or_injecting_restore_the_provider() {
+        let mut collection = ServiceCollection::new();
+        collection
+            .add_singleton_boxed::<u8, _>(|_| panic!("u8 factory"))
+            .add_singleton_boxed::<u64, _>(|_| Box::new(64))
+            .add_scoped_injectable(|_| Unbindable);
+
+        let provider = Rc::new(collection.build());
+        let cached = provider.get_boxed::<u64>().unwrap();
+
+        let traced = catch_unwind(AssertUnwindSafe(|| provider.trace_graph()));
+        assert!(traced.is_err());
+        assert!(provider.trace.borrow().is_none());
+
+        let mut overrides = Overrides::new();
+        overrides.add_boxed::<u64>(Box::new(0));
+        let overridden = catch_unwind(AssertUnwindSafe(|| {
+            provider.get_with_overrides_boxed::<u8>(overrides)
+        }));
+        assert!(overridden.is_err());
+        assert!(provider.overrides.borrow().is_none());
+
+        let tree = catch_unwind(AssertUnwindSafe(|| provider.resolve_tree_boxed::<u8>()));
+        assert!(tree.is_err());
+        assert!(provider.recorded.borrow().is_none());
+
+        let scope = provider.create_scope();
+        let injected = catch_unwind(AssertUnwindSafe(|| scope.get::<Unbindable>()));
+        assert!(injected.is_err());
+        assert!(provider.scopes.borrow().is_empty());
+        assert!(provider.injections.borrow().is_empty());
+
+        assert!(Rc::ptr_eq(&cached, &provider.get_boxed::<u64>().unwrap()));
+        assert_eq!(provider.cached_instances(), 1);
+    }
+}